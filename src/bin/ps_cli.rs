@@ -1,87 +1,1416 @@
-use clap::Parser;
+use ::rand::random;
+use clap::{Parser, Subcommand, ValueEnum};
 
+#[path = "../clusters.rs"]
+mod clusters;
 #[path = "../core.rs"]
 mod core;
+#[path = "../correlation.rs"]
+mod correlation;
+#[path = "../fft.rs"]
+mod fft;
+#[path = "../interface.rs"]
+mod interface;
+#[path = "../lattice_image.rs"]
+mod lattice_image;
+#[path = "../mean_field.rs"]
+mod mean_field;
+#[path = "../multicanonical.rs"]
+mod multicanonical;
+#[path = "../onsager.rs"]
+mod onsager;
+#[path = "../paint.rs"]
+mod paint;
+#[path = "../protocol.rs"]
+mod protocol;
+#[path = "../quasi_chemical.rs"]
+mod quasi_chemical;
+#[path = "../replica_exchange.rs"]
+mod replica_exchange;
+#[path = "../reweighting.rs"]
+mod reweighting;
+#[path = "../structure_factor.rs"]
+mod structure_factor;
+#[path = "../wang_landau.rs"]
+mod wang_landau;
 use core::{Lattice, SimulationLogger};
+use correlation::PairCorrelation;
+use lattice_image::GifRecorder;
+use mean_field::{MeanField, Theory};
+use multicanonical::Multicanonical;
+use onsager::Onsager;
+use protocol::{Action, Parameter};
+use quasi_chemical::QuasiChemical;
+use replica_exchange::ReplicaExchange;
+use reweighting::{Histogram, Reweighting};
+use std::io::Write;
+use structure_factor::StructureFactor;
+use wang_landau::{FlatHistogram, Macrostate, WangLandau};
 
 #[derive(Parser, Debug)]
 #[command(name = "ps_cli", about = "Headless lattice simulator runner")]
 struct Args {
-    #[arg(long, default_value_t = 150)]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(long, global = true, default_value_t = 150)]
     width: usize,
 
-    #[arg(long, default_value_t = 150)]
+    #[arg(long, global = true, default_value_t = 150)]
     height: usize,
 
-    #[arg(long, default_value_t = 10_000)]
+    #[arg(long, global = true, default_value_t = 10_000)]
     steps: u64,
 
-    #[arg(long, default_value_t = 1.2)]
+    #[arg(long, global = true, default_value_t = 1.2)]
     temperature: f32,
 
-    #[arg(long, default_value_t = -2.0, allow_hyphen_values = true)]
+    #[arg(long, global = true, default_value_t = -2.0, allow_hyphen_values = true)]
     chem_potential: f32,
 
-    #[arg(long, default_value = "data/run.csv")]
+    #[arg(long, global = true, default_value = "data/run.csv")]
     output: String,
 
     #[arg(long)]
     snapshot_csv: Option<String>,
 
-    #[arg(long, default_value_t = 1.0)]
+    /// PNG of the final configuration in the GUI colours.
+    #[arg(long)]
+    snapshot_png: Option<String>,
+
+    /// Animated GIF of the run, one frame every `--frame-every` sweeps.
+    #[arg(long)]
+    gif: Option<String>,
+
+    /// Directory for a numbered PNG sequence, one frame every `--frame-every`
+    /// sweeps (`frame_000000.png`, …).
+    #[arg(long)]
+    frames_dir: Option<String>,
+
+    #[arg(long, default_value_t = 100)]
+    frame_every: u64,
+
+    /// GIF frame duration in hundredths of a second.
+    #[arg(long, default_value_t = 5)]
+    gif_delay: u16,
+
+    /// Pixels per lattice site in PNG and GIF output.
+    #[arg(long, default_value_t = 4)]
+    image_scale: usize,
+
+    #[arg(long, global = true, default_value_t = 1.0)]
     interaction: f32,
 
-    #[arg(long, default_value_t = 0.0)]
+    #[arg(long, global = true, default_value_t = 0.0)]
     epsilon0: f32,
 
-    #[arg(long, default_value_t = 0.0)]
+    #[arg(long, global = true, default_value_t = 0.0)]
     alpha: f32,
 
-    #[arg(long, default_value_t = 0.5)]
+    #[arg(long, global = true, default_value_t = 0.5)]
     init_density: f32,
+
+    /// Sweeps discarded before observables are accumulated; defaults to `steps / 2`.
+    #[arg(long, global = true)]
+    equilibration: Option<u64>,
+
+    /// Sweeps between measurements after equilibration.
+    #[arg(long, global = true, default_value_t = 10)]
+    measure_every: u64,
+
+    /// Radially averaged and axis pair correlation G(r), with the fitted
+    /// correlation length in a leading `#` comment line.
+    #[arg(long)]
+    correlation_csv: Option<String>,
+
+    /// Radially averaged structure factor S(|q|).
+    #[arg(long)]
+    structure_factor_csv: Option<String>,
+
+    /// Full structure factor S(qx, qy) in long format.
+    #[arg(long)]
+    structure_factor_map_csv: Option<String>,
+
+    /// Interface length, cluster perimeters and the boundary fractal dimension
+    /// of the largest cluster at every measurement sweep.
+    #[arg(long)]
+    interface_csv: Option<String>,
+
+    /// Size, perimeter and percolation flags of every cluster in the final
+    /// configuration (periodic labelling).
+    #[arg(long)]
+    clusters_csv: Option<String>,
+
+    /// Joint histogram of occupied bonds and molecule count over the
+    /// measurement sweeps, the input of `reweight`.
+    #[arg(long)]
+    histogram_csv: Option<String>,
+
+    /// Session protocol saved by the GUI. Its seed, lattice size, initial
+    /// density, couplings, (T, µ) schedule, brush strokes and length replace
+    /// the corresponding options, reproducing the recorded run.
+    #[arg(long)]
+    protocol: Option<String>,
+}
+
+/// A GUI session protocol: the seed of the first lattice and the actions
+/// after its construction.
+struct Protocol {
+    seed: u64,
+    actions: Vec<(u64, Action)>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Dynamics {
+    /// Single-site insertion/removal (`Lattice::step`), density not conserved.
+    Metropolis,
+    /// Nearest-neighbour hops (`Lattice::step_kawasaki`), density conserved.
+    Kawasaki,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum WangLandauStates {
+    /// ln g(B, N) over occupied bonds and molecule count.
+    Joint,
+    /// ln g(H) over H = E − µN at `--chem-potential`.
+    Energy,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Approximation {
+    /// Bragg–Williams mean field.
+    MeanField,
+    /// Bethe–Peierls / quasi-chemical pair approximation.
+    QuasiChemical,
+    /// Onsager/Yang exact solution; requires `--alpha 0` and has no spinodal.
+    Exact,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Spanning and wrapping probability vs density over independent replicas.
+    ///
+    /// For every temperature and every chemical potential on the grid, `--replicas`
+    /// lattices are equilibrated for `--steps` sweeps and their final configurations
+    /// are tested for percolation. One CSV row is written per (T, µ) point.
+    PercolationScan {
+        /// Comma-separated temperatures; defaults to `--temperature`.
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+        temperatures: Vec<f32>,
+
+        #[arg(long, default_value_t = -3.0, allow_hyphen_values = true)]
+        mu_min: f32,
+
+        #[arg(long, default_value_t = 1.0, allow_hyphen_values = true)]
+        mu_max: f32,
+
+        #[arg(long, default_value_t = 21)]
+        mu_points: usize,
+
+        #[arg(long, default_value_t = 20)]
+        replicas: usize,
+    },
+
+    /// Domain coarsening after a quench from a random configuration.
+    ///
+    /// A lattice filled at `--init-density` (the infinite-temperature state) is
+    /// evolved at `--temperature` for `--steps` sweeps. At logarithmically spaced
+    /// sweeps the interface density and the structure factor are measured and
+    /// averaged over `--replicas` independent quenches. The CSV holds the domain
+    /// size both from the interface density, `1 / (2 · interface_density)`, and
+    /// from the first moment of S(q), `2π / ⟨|q|⟩`.
+    Quench {
+        #[arg(long, value_enum, default_value_t = Dynamics::Kawasaki)]
+        dynamics: Dynamics,
+
+        #[arg(long, default_value_t = 40)]
+        log_points: usize,
+
+        #[arg(long, default_value_t = 1)]
+        replicas: usize,
+
+        /// Upper wave number of the S(q) first moment, in units of π.
+        #[arg(long, default_value_t = 0.5)]
+        q_max: f64,
+    },
+
+    /// Histogram reweighting of one or more `--histogram-csv` files.
+    ///
+    /// The histograms are combined with the multi-histogram (WHAM) equations and
+    /// density, energy, heat capacity and susceptibility per site are written for
+    /// every point of the (T, µ) grid. Ranges default to the span of the input
+    /// runs; reweighting is only reliable close to the sampled states.
+    Reweight {
+        /// Comma-separated histogram CSV files.
+        #[arg(long, value_delimiter = ',', required = true)]
+        histograms: Vec<String>,
+
+        #[arg(long)]
+        t_min: Option<f64>,
+
+        #[arg(long)]
+        t_max: Option<f64>,
+
+        #[arg(long, default_value_t = 101)]
+        t_points: usize,
+
+        #[arg(long, allow_hyphen_values = true)]
+        mu_min: Option<f64>,
+
+        #[arg(long, allow_hyphen_values = true)]
+        mu_max: Option<f64>,
+
+        #[arg(long, default_value_t = 1)]
+        mu_points: usize,
+
+        #[arg(long, default_value_t = 1e-8)]
+        tolerance: f64,
+
+        #[arg(long, default_value_t = 10_000)]
+        max_iterations: usize,
+    },
+
+    /// Replica exchange (parallel tempering) over a ladder of temperatures
+    /// and/or chemical potentials.
+    ///
+    /// Each rung runs its own lattice; every `--swap-every` sweeps neighbouring
+    /// rungs attempt to exchange configurations. `--output` receives one row per
+    /// rung with the measured observables and the swap acceptance rate to the
+    /// next rung.
+    ReplicaExchange {
+        /// Comma-separated temperature ladder; a single value (or
+        /// `--temperature`) is shared by all rungs.
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+        temperatures: Vec<f32>,
+
+        /// Comma-separated chemical-potential ladder; a single value (or
+        /// `--chem-potential`) is shared by all rungs.
+        #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+        chem_potentials: Vec<f32>,
+
+        #[arg(long, default_value_t = 1)]
+        swap_every: u64,
+
+        /// Density of every rung at each measurement sweep.
+        #[arg(long)]
+        trace_csv: Option<String>,
+    },
+
+    /// Wang–Landau estimate of the density of states.
+    ///
+    /// Uses the insertion/removal moves of the Metropolis sweep. `--output`
+    /// receives `bonds,molecules,energy,ln_g` for `--macrostate joint` or
+    /// `h,ln_g` for `--macrostate energy`. `--steps` is not used; the run stops
+    /// once ln f drops below `--ln-f-final` or after `--max-sweeps`.
+    WangLandau {
+        #[arg(long, value_enum, default_value_t = WangLandauStates::Joint)]
+        macrostate: WangLandauStates,
+
+        /// Width of the H bins for `--macrostate energy`.
+        #[arg(long, default_value_t = 0.5)]
+        bin_width: f64,
+
+        #[arg(long, default_value_t = 0)]
+        n_min: usize,
+
+        /// Defaults to the number of sites.
+        #[arg(long)]
+        n_max: Option<usize>,
+
+        #[arg(long, default_value_t = 0.8)]
+        flatness: f64,
+
+        #[arg(long, default_value_t = 1e-6)]
+        ln_f_final: f64,
+
+        /// Sweeps between flatness checks.
+        #[arg(long, default_value_t = 100)]
+        check_every: u64,
+
+        #[arg(long, default_value_t = 100_000_000)]
+        max_sweeps: u64,
+
+        /// Seed of the starting lattice and the walk; random by default.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Multicanonical sampling in N: free-energy profile across coexistence.
+    ///
+    /// The weight function is built with Wang–Landau updates in N until ln f
+    /// drops below `--ln-f-final` (or `--max-sweeps`), then frozen for
+    /// `--steps` production sweeps. `--output` receives
+    /// `molecules,density,ln_p,ln_p_per_site,mean_field,quasi_chemical,visits`,
+    /// where the last two theories' `ln P(ρ)` per site are shifted to the same
+    /// zero.
+    Multicanonical {
+        #[arg(long, default_value_t = 0)]
+        n_min: usize,
+
+        /// Defaults to the number of sites.
+        #[arg(long)]
+        n_max: Option<usize>,
+
+        #[arg(long, default_value_t = 0.8)]
+        flatness: f64,
+
+        #[arg(long, default_value_t = 1e-4)]
+        ln_f_final: f64,
+
+        /// Sweeps between flatness checks.
+        #[arg(long, default_value_t = 100)]
+        check_every: u64,
+
+        #[arg(long, default_value_t = 10_000_000)]
+        max_sweeps: u64,
+
+        /// Seed of the starting lattice and the walk; random by default.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Analytic binodal, spinodal and critical point for the model
+    /// parameters.
+    ///
+    /// No simulation is run. `--output` receives one row per temperature from
+    /// `--t-min` up to the critical temperature:
+    /// `temperature,binodal_mu,binodal_low,binodal_high,spinodal_low,spinodal_high,spinodal_mu_low,spinodal_mu_high`,
+    /// closed by a row at the critical point. Spinodal columns are empty for
+    /// `--theory exact`.
+    PhaseBoundaries {
+        #[arg(long, value_enum, default_value_t = Approximation::MeanField)]
+        theory: Approximation,
+
+        #[arg(long, default_value_t = 0.01)]
+        t_min: f64,
+
+        #[arg(long, default_value_t = 100)]
+        t_points: usize,
+    },
 }
 
 fn main() -> std::io::Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let protocol = match args.protocol.clone() {
+        Some(_) if args.command.is_some() => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--protocol only applies to a plain run",
+            ));
+        }
+        Some(path) => {
+            let protocol = load_protocol(&mut args, &path)?;
+            let changes_ensemble = protocol
+                .actions
+                .iter()
+                .any(|(_, action)| matches!(action, Action::Set(..)));
+            if args.histogram_csv.is_some() && changes_ensemble {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "--histogram-csv needs one (T, µ, J, ε0, α) but the protocol changes them after sweep 0",
+                ));
+            }
+            Some(protocol)
+        }
+        None => None,
+    };
+
+    match &args.command {
+        None => run(&args, protocol),
+        Some(Command::PercolationScan {
+            temperatures,
+            mu_min,
+            mu_max,
+            mu_points,
+            replicas,
+        }) => {
+            let temperatures = if temperatures.is_empty() {
+                vec![args.temperature]
+            } else {
+                temperatures.clone()
+            };
+            percolation_scan(
+                &args,
+                &temperatures,
+                (*mu_min, *mu_max),
+                *mu_points,
+                *replicas,
+            )
+        }
+        Some(Command::Quench {
+            dynamics,
+            log_points,
+            replicas,
+            q_max,
+        }) => quench(
+            &args,
+            *dynamics,
+            *log_points,
+            *replicas,
+            q_max * std::f64::consts::PI,
+        ),
+        Some(Command::Reweight {
+            histograms,
+            t_min,
+            t_max,
+            t_points,
+            mu_min,
+            mu_max,
+            mu_points,
+            tolerance,
+            max_iterations,
+        }) => {
+            let histograms: Vec<Histogram> = histograms
+                .iter()
+                .map(Histogram::load_csv)
+                .collect::<std::io::Result<_>>()?;
+            let span = |values: Vec<f64>| {
+                let lo = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let hi = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                (lo, hi)
+            };
+            let (t_lo, t_hi) = span(histograms.iter().map(|h| h.temperature as f64).collect());
+            let (mu_lo, mu_hi) = span(histograms.iter().map(|h| h.chem_potential as f64).collect());
+            reweight(
+                &args,
+                &histograms,
+                (t_min.unwrap_or(t_lo), t_max.unwrap_or(t_hi), *t_points),
+                (mu_min.unwrap_or(mu_lo), mu_max.unwrap_or(mu_hi), *mu_points),
+                *tolerance,
+                *max_iterations,
+            )
+        }
+        Some(Command::ReplicaExchange {
+            temperatures,
+            chem_potentials,
+            swap_every,
+            trace_csv,
+        }) => {
+            let temperatures = if temperatures.is_empty() {
+                vec![args.temperature]
+            } else {
+                temperatures.clone()
+            };
+            let chem_potentials = if chem_potentials.is_empty() {
+                vec![args.chem_potential]
+            } else {
+                chem_potentials.clone()
+            };
+            let rungs = temperatures.len().max(chem_potentials.len());
+            if [temperatures.len(), chem_potentials.len()]
+                .iter()
+                .any(|&n| n != 1 && n != rungs)
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "--temperatures and --chem-potentials must have the same length or a single value",
+                ));
+            }
+            let ladder = (0..rungs)
+                .map(|k| {
+                    (
+                        temperatures[k.min(temperatures.len() - 1)],
+                        chem_potentials[k.min(chem_potentials.len() - 1)],
+                    )
+                })
+                .collect();
+            replica_exchange(&args, ladder, *swap_every, trace_csv.as_deref())
+        }
+        Some(Command::WangLandau {
+            macrostate,
+            bin_width,
+            n_min,
+            n_max,
+            flatness,
+            ln_f_final,
+            check_every,
+            max_sweeps,
+            seed,
+        }) => {
+            let macrostate = match macrostate {
+                WangLandauStates::Joint => Macrostate::Joint,
+                WangLandauStates::Energy => Macrostate::Energy {
+                    chem_potential: args.chem_potential as f64,
+                    bin_width: *bin_width,
+                },
+            };
+            let mut wl = WangLandau::new(
+                seeded_lattice(&args, *seed),
+                macrostate,
+                (*n_min, n_max.unwrap_or(args.width * args.height)),
+                *flatness,
+            );
+            wang_landau(&args, &mut wl, *ln_f_final, *check_every, *max_sweeps)
+        }
+        Some(Command::Multicanonical {
+            n_min,
+            n_max,
+            flatness,
+            ln_f_final,
+            check_every,
+            max_sweeps,
+            seed,
+        }) => {
+            let mut muca = Multicanonical::new(
+                seeded_lattice(&args, *seed),
+                args.temperature,
+                args.chem_potential,
+                (*n_min, n_max.unwrap_or(args.width * args.height)),
+                *flatness,
+            );
+            multicanonical(&args, &mut muca, *ln_f_final, *check_every, *max_sweeps)
+        }
+        Some(Command::PhaseBoundaries {
+            theory,
+            t_min,
+            t_points,
+        }) => {
+            let (j, epsilon0, alpha) = (args.interaction, args.epsilon0, args.alpha);
+            let theory: Box<dyn Theory> = match theory {
+                Approximation::MeanField => Box::new(MeanField { j, epsilon0, alpha }),
+                Approximation::QuasiChemical => Box::new(QuasiChemical { j, epsilon0, alpha }),
+                Approximation::Exact => Box::new(Onsager { j, epsilon0, alpha }),
+            };
+            phase_boundaries(&args, theory.as_ref(), *t_min, *t_points)
+        }
+    }
+}
 
-    let mut lattice = Lattice::new_with_params(
+fn new_lattice(args: &Args) -> Lattice {
+    Lattice::new_with_params(
         args.width,
         args.height,
         args.interaction,
         args.epsilon0,
         args.alpha,
         args.init_density,
-    );
+    )
+}
+
+/// A lattice from `seed`, or from a random seed that is printed so the run
+/// can be repeated.
+fn seeded_lattice(args: &Args, seed: Option<u64>) -> Lattice {
+    let seed = seed.unwrap_or_else(random);
+    eprintln!("[ps_cli] seed {seed}");
+    Lattice::new_seeded(
+        args.width,
+        args.height,
+        args.interaction,
+        args.epsilon0,
+        args.alpha,
+        args.init_density,
+        seed,
+    )
+}
+
+/// Reads a protocol, moving its initial settings (everything up to the
+/// first rebuild at sweep 0) and its length into `args`.
+fn load_protocol(args: &mut Args, path: &str) -> std::io::Result<Protocol> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    read_protocol(args, file).map_err(|e| std::io::Error::new(e.kind(), format!("{path}: {e}")))
+}
+
+fn read_protocol(args: &mut Args, input: impl std::io::BufRead) -> std::io::Result<Protocol> {
+    let mut actions = protocol::read(input, Action::parse)?.into_iter();
+    let invalid =
+        |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+    let seed = loop {
+        match actions.next() {
+            Some((0, Action::Set(parameter, value))) => set_parameter(args, parameter, value),
+            Some((
+                0,
+                Action::Rebuild {
+                    seed,
+                    width,
+                    height,
+                    init_density,
+                },
+            )) => {
+                args.width = width;
+                args.height = height;
+                args.init_density = init_density;
+                break seed;
+            }
+            _ => return Err(invalid("the protocol must start with a rebuild at sweep 0")),
+        }
+    };
+    let actions: Vec<_> = actions.collect();
+    for (_, action) in &actions {
+        match *action {
+            Action::Rebuild { width, height, .. }
+                if (width, height) != (args.width, args.height) =>
+            {
+                return Err(invalid(
+                    "the lattice size must not change during a protocol",
+                ));
+            }
+            _ => {}
+        }
+    }
+    if let Some(&(end, _)) = actions.iter().find(|(_, a)| *a == Action::End) {
+        args.steps = end;
+    }
+    Ok(Protocol { seed, actions })
+}
+
+fn set_parameter(args: &mut Args, parameter: Parameter, value: f32) {
+    match parameter {
+        Parameter::Temperature => args.temperature = value,
+        Parameter::ChemPotential => args.chem_potential = value,
+        Parameter::Interaction => args.interaction = value,
+        Parameter::Epsilon0 => args.epsilon0 = value,
+        Parameter::Alpha => args.alpha = value,
+    }
+}
+
+fn run(args: &Args, protocol: Option<Protocol>) -> std::io::Result<()> {
+    let (mut lattice, actions) = match protocol {
+        Some(Protocol { seed, actions }) => {
+            let lattice = Lattice::new_seeded(
+                args.width,
+                args.height,
+                args.interaction,
+                args.epsilon0,
+                args.alpha,
+                args.init_density,
+                seed,
+            );
+            (lattice, actions)
+        }
+        None => (new_lattice(args), Vec::new()),
+    };
+    let mut actions = actions.into_iter().peekable();
+    let (mut temperature, mut chem_potential) = (args.temperature, args.chem_potential);
     let mut logger = SimulationLogger::new();
+    let mut correlation = args
+        .correlation_csv
+        .as_ref()
+        .map(|_| PairCorrelation::new(args.width, args.height));
+    let mut structure_factor = (args.structure_factor_csv.is_some()
+        || args.structure_factor_map_csv.is_some())
+    .then(|| StructureFactor::new(args.width, args.height));
+    let mut interface_file = match &args.interface_csv {
+        Some(path) => {
+            let mut file = create_output(path)?;
+            writeln!(
+                file,
+                "step,interface_length,interface_density,clusters,largest_cluster,largest_perimeter,mean_perimeter,boundary_dimension"
+            )?;
+            Some(file)
+        }
+        None => None,
+    };
+    let mut histogram = args
+        .histogram_csv
+        .as_ref()
+        .map(|_| Histogram::new(&lattice, args.temperature, args.chem_potential));
+    let equilibration = args.equilibration.unwrap_or(args.steps / 2);
+    let measure_every = args.measure_every.max(1);
+    let frame_every = args.frame_every.max(1);
+    let mut gif = match &args.gif {
+        Some(path) => Some(GifRecorder::new(
+            std::io::BufWriter::new(create_output(path)?),
+            &lattice,
+            args.image_scale,
+            args.gif_delay,
+        )?),
+        None => None,
+    };
+    if let Some(dir) = &args.frames_dir {
+        std::fs::create_dir_all(dir)?;
+    }
 
     let progress_interval = (args.steps / 20).max(1);
     for step in 1..=args.steps {
-        lattice.step(args.temperature, args.chem_potential);
+        // Actions at sweep `s` take effect before sweep `s + 1`.
+        while let Some((_, action)) = actions.next_if(|&(sweep, _)| sweep < step) {
+            match action {
+                Action::Set(Parameter::Temperature, value) => temperature = value,
+                Action::Set(Parameter::ChemPotential, value) => chem_potential = value,
+                Action::Set(Parameter::Interaction, value) => lattice.j = value,
+                Action::Set(Parameter::Epsilon0, value) => lattice.epsilon0 = value,
+                Action::Set(Parameter::Alpha, value) => lattice.alpha = value,
+                _ => action.apply_to_lattice(&mut lattice),
+            }
+        }
+        lattice.step(temperature, chem_potential);
         if step % progress_interval == 0 {
             let pct = (step as f32 / args.steps as f32) * 100.0;
             eprintln!("[ps_cli] progress: {pct:.0}%");
         }
-        let density = lattice.molecule_count() as f32 / (args.width * args.height) as f32;
-        logger.record(step, args.temperature, args.chem_potential, density);
+        logger.record(step, temperature, chem_potential, &lattice);
+
+        if step.is_multiple_of(frame_every) {
+            if let Some(gif) = gif.as_mut() {
+                gif.add_frame(&lattice)?;
+            }
+            if let Some(dir) = &args.frames_dir {
+                let path = std::path::Path::new(dir).join(format!("frame_{step:06}.png"));
+                let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                lattice_image::write_png(&lattice, args.image_scale, file)?;
+            }
+        }
+
+        if step > equilibration && (step - equilibration).is_multiple_of(measure_every) {
+            if let Some(c) = correlation.as_mut() {
+                c.accumulate(&lattice);
+            }
+            if let Some(s) = structure_factor.as_mut() {
+                s.accumulate(&lattice);
+            }
+            if let Some(file) = interface_file.as_mut() {
+                write_interface_row(file, step, &lattice)?;
+            }
+            if let Some(h) = histogram.as_mut() {
+                h.record(&lattice);
+            }
+        }
     }
 
-    logger.save_csv(args.output)?;
+    logger.save_csv(&args.output)?;
 
-    if let Some(path) = args.snapshot_csv {
-        save_lattice_snapshot_csv(&lattice, &path)?;
+    if let (Some(path), Some(c)) = (&args.correlation_csv, &correlation) {
+        save_correlation_csv(c, path)?;
+    }
+    if let Some(s) = &structure_factor {
+        if let Some(path) = &args.structure_factor_csv {
+            save_structure_factor_csv(s, path)?;
+        }
+        if let Some(path) = &args.structure_factor_map_csv {
+            save_structure_factor_map_csv(s, path)?;
+        }
+    }
+
+    if let (Some(path), Some(h)) = (&args.histogram_csv, &histogram) {
+        if h.samples() == 0 {
+            eprintln!("[ps_cli] warning: no measurement sweeps, histogram is empty");
+        }
+        h.save_csv(path)?;
+    }
+
+    if let Some(path) = &args.clusters_csv {
+        save_clusters_csv(&lattice, path)?;
+    }
+
+    if let Some(path) = &args.snapshot_csv {
+        save_lattice_snapshot_csv(&lattice, path)?;
+    }
+    if let Some(path) = &args.snapshot_png {
+        let file = std::io::BufWriter::new(create_output(path)?);
+        lattice_image::write_png(&lattice, args.image_scale, file)?;
+    }
+    if let Some(gif) = gif {
+        eprintln!("[ps_cli] wrote {} GIF frames", gif.frames());
+        gif.finish()?;
     }
 
     Ok(())
 }
 
-fn save_lattice_snapshot_csv(lattice: &Lattice, path: &str) -> std::io::Result<()> {
-    use std::io::Write;
-    if let Some(dir) = std::path::Path::new(path).parent() {
-        if !dir.as_os_str().is_empty() {
-            std::fs::create_dir_all(dir)?;
+fn percolation_scan(
+    args: &Args,
+    temperatures: &[f32],
+    mu_range: (f32, f32),
+    mu_points: usize,
+    replicas: usize,
+) -> std::io::Result<()> {
+    let mut file = create_output(&args.output)?;
+    writeln!(
+        file,
+        "temperature,chem_potential,replicas,density,largest_cluster,p_span_x,p_span_y,p_span_any,p_wrap_x,p_wrap_y,p_wrap_any"
+    )?;
+
+    let n_sites = (args.width * args.height) as f32;
+    let total = temperatures.len() * mu_points;
+    let mut done = 0;
+    for &temp in temperatures {
+        for k in 0..mu_points {
+            let mu = if mu_points > 1 {
+                mu_range.0 + (k as f32 / (mu_points - 1) as f32) * (mu_range.1 - mu_range.0)
+            } else {
+                mu_range.0
+            };
+
+            let mut density = 0.0;
+            let mut largest = 0.0;
+            let mut counts = [0usize; 6];
+            for _ in 0..replicas {
+                let mut lattice = new_lattice(args);
+                for _ in 0..args.steps {
+                    lattice.step(temp, mu);
+                }
+                density += lattice.molecule_count() as f32 / n_sites;
+                let periodic = lattice.clusters(clusters::Boundary::Periodic);
+                largest += periodic.largest_size() as f32 / n_sites;
+                let open = lattice.clusters(clusters::Boundary::Open);
+                let p = clusters::Percolation::of(&open, &periodic);
+                let flags = [
+                    p.spans_x,
+                    p.spans_y,
+                    p.spans_x || p.spans_y,
+                    p.wraps_x,
+                    p.wraps_y,
+                    p.wraps_x || p.wraps_y,
+                ];
+                for (count, flag) in counts.iter_mut().zip(flags) {
+                    *count += flag as usize;
+                }
+            }
+
+            let r = replicas.max(1) as f32;
+            let [sx, sy, sa, wx, wy, wa] = counts.map(|c| c as f32 / r);
+            writeln!(
+                file,
+                "{temp},{mu},{replicas},{},{},{sx},{sy},{sa},{wx},{wy},{wa}",
+                density / r,
+                largest / r
+            )?;
+
+            done += 1;
+            eprintln!("[ps_cli] percolation scan: {done}/{total} (T={temp}, µ={mu})");
+        }
+    }
+    Ok(())
+}
+
+fn grid_point(range: (f64, f64, usize), k: usize) -> f64 {
+    let (lo, hi, points) = range;
+    if points > 1 {
+        lo + (k as f64 / (points - 1) as f64) * (hi - lo)
+    } else {
+        lo
+    }
+}
+
+fn reweight(
+    args: &Args,
+    histograms: &[Histogram],
+    t_range: (f64, f64, usize),
+    mu_range: (f64, f64, usize),
+    tolerance: f64,
+    max_iterations: usize,
+) -> std::io::Result<()> {
+    let reweighting = Reweighting::new(histograms, tolerance, max_iterations)?;
+    eprintln!(
+        "[ps_cli] reweight: {} histograms combined in {} iterations",
+        histograms.len(),
+        reweighting.iterations()
+    );
+
+    let mut file = create_output(&args.output)?;
+    writeln!(
+        file,
+        "temperature,chem_potential,density,energy,heat_capacity,susceptibility"
+    )?;
+    for m in 0..mu_range.2.max(1) {
+        let mu = grid_point(mu_range, m);
+        let mut peak_c = (f64::NAN, f64::NEG_INFINITY);
+        let mut peak_chi = (f64::NAN, f64::NEG_INFINITY);
+        for k in 0..t_range.2.max(1) {
+            let t = grid_point(t_range, k);
+            let obs = reweighting.observables(t, mu);
+            writeln!(
+                file,
+                "{t},{mu},{},{},{},{}",
+                obs.density, obs.energy, obs.heat_capacity, obs.susceptibility
+            )?;
+            if obs.heat_capacity > peak_c.1 {
+                peak_c = (t, obs.heat_capacity);
+            }
+            if obs.susceptibility > peak_chi.1 {
+                peak_chi = (t, obs.susceptibility);
+            }
+        }
+        eprintln!(
+            "[ps_cli] µ={mu}: heat capacity peak at T={:.5}, susceptibility peak at T={:.5}",
+            peak_c.0, peak_chi.0
+        );
+    }
+    Ok(())
+}
+
+fn replica_exchange(
+    args: &Args,
+    ladder: Vec<(f32, f32)>,
+    swap_every: u64,
+    trace_csv: Option<&str>,
+) -> std::io::Result<()> {
+    let mut exchange = ReplicaExchange::new(ladder, || new_lattice(args));
+    let rungs = exchange.ladder.len();
+    let n_sites = (args.width * args.height) as f64;
+    let equilibration = args.equilibration.unwrap_or(args.steps / 2);
+    let measure_every = args.measure_every.max(1);
+    let swap_every = swap_every.max(1);
+
+    let mut trace = match trace_csv {
+        Some(path) => {
+            let mut file = create_output(path)?;
+            let columns: Vec<String> = (0..rungs).map(|k| format!("density_{k}")).collect();
+            writeln!(file, "step,{}", columns.join(","))?;
+            Some(file)
+        }
+        None => None,
+    };
+
+    // Per rung: Σρ, Σρ², Σ e, number of samples.
+    let mut sums = vec![(0.0, 0.0, 0.0); rungs];
+    let mut samples = 0u64;
+    let progress_interval = (args.steps / 20).max(1);
+    for step in 1..=args.steps {
+        exchange.sweep();
+        if step.is_multiple_of(swap_every) {
+            exchange.attempt_swaps();
+        }
+        if step.is_multiple_of(progress_interval) {
+            let pct = (step as f32 / args.steps as f32) * 100.0;
+            eprintln!("[ps_cli] progress: {pct:.0}%");
+        }
+        if step > equilibration && (step - equilibration).is_multiple_of(measure_every) {
+            let densities: Vec<f64> = exchange
+                .lattices
+                .iter()
+                .map(|l| l.molecule_count() as f64 / n_sites)
+                .collect();
+            for (k, lattice) in exchange.lattices.iter().enumerate() {
+                sums[k].0 += densities[k];
+                sums[k].1 += densities[k] * densities[k];
+                sums[k].2 += lattice.energy() / n_sites;
+            }
+            samples += 1;
+            if let Some(file) = trace.as_mut() {
+                let row: Vec<String> = densities.iter().map(|d| d.to_string()).collect();
+                writeln!(file, "{step},{}", row.join(","))?;
+            }
+        }
+    }
+
+    let rates = exchange.acceptance_rates();
+    let s = samples.max(1) as f64;
+    let mut file = create_output(&args.output)?;
+    writeln!(
+        file,
+        "temperature,chem_potential,density,density_std,energy,swap_acceptance"
+    )?;
+    for (k, &(temp, mu)) in exchange.ladder.iter().enumerate() {
+        let (sum_rho, sum_rho2, sum_e) = sums[k];
+        let mean = sum_rho / s;
+        let std = (sum_rho2 / s - mean * mean).max(0.0).sqrt();
+        let rate = rates.get(k).map(|r| r.to_string()).unwrap_or_default();
+        writeln!(file, "{temp},{mu},{mean},{std},{},{rate}", sum_e / s)?;
+    }
+    let formatted: Vec<String> = rates.iter().map(|r| format!("{r:.3}")).collect();
+    eprintln!(
+        "[ps_cli] replica exchange: swap acceptance [{}]",
+        formatted.join(", ")
+    );
+    Ok(())
+}
+
+/// Refines `sampler` until `ln f` drops below `ln_f_final`, checking for a
+/// flat histogram every `check_every` sweeps, or until `max_sweeps`.
+fn flatten(
+    name: &str,
+    sampler: &mut impl FlatHistogram,
+    ln_f_final: f64,
+    check_every: u64,
+    max_sweeps: u64,
+) {
+    let check_every = check_every.max(1);
+    let mut sweeps = 0;
+    while sampler.ln_f() >= ln_f_final && sweeps < max_sweeps {
+        sampler.sweep();
+        sweeps += 1;
+        if sweeps.is_multiple_of(check_every) && sampler.is_flat() {
+            eprintln!(
+                "[ps_cli] {name}: flat at ln f = {:e} after {sweeps} sweeps ({} macrostates)",
+                sampler.ln_f(),
+                sampler.macrostates()
+            );
+            sampler.refine();
+        }
+    }
+    if sampler.ln_f() >= ln_f_final {
+        eprintln!(
+            "[ps_cli] warning: stopped after {max_sweeps} sweeps at ln f = {:e}",
+            sampler.ln_f()
+        );
+    }
+}
+
+fn wang_landau(
+    args: &Args,
+    wl: &mut WangLandau,
+    ln_f_final: f64,
+    check_every: u64,
+    max_sweeps: u64,
+) -> std::io::Result<()> {
+    flatten("wang-landau", wl, ln_f_final, check_every, max_sweeps);
+
+    let mut file = create_output(&args.output)?;
+    let joint = wl.macrostate() == Macrostate::Joint;
+    if joint {
+        writeln!(file, "bonds,molecules,energy,ln_g")?;
+    } else {
+        writeln!(file, "h,ln_g")?;
+    }
+    for (key, ln_g) in wl.ln_g() {
+        let energy = wl.key_energy(key);
+        if joint {
+            writeln!(file, "{},{},{energy},{ln_g}", key.0, key.1)?;
+        } else {
+            writeln!(file, "{energy},{ln_g}")?;
         }
     }
-    let mut file = std::fs::File::create(path)?;
+    Ok(())
+}
+
+fn multicanonical(
+    args: &Args,
+    muca: &mut Multicanonical,
+    ln_f_final: f64,
+    check_every: u64,
+    max_sweeps: u64,
+) -> std::io::Result<()> {
+    flatten("multicanonical", muca, ln_f_final, check_every, max_sweeps);
+    muca.freeze();
+    for _ in 0..args.steps {
+        muca.sweep();
+    }
+
+    let sites = (args.width * args.height) as f64;
+    let profile = muca.ln_p();
+    let model = MeanField::from_lattice(&muca.lattice);
+    let quasi_chemical = QuasiChemical {
+        j: model.j,
+        epsilon0: model.epsilon0,
+        alpha: model.alpha,
+    };
+    let d = |n: usize| (n as f64 / sites) as f32;
+    let (t, mu) = (args.temperature, args.chem_potential);
+    let mean_field = |n: usize| model.calculate_ftc(d(n), t, mu) as f64;
+    let qc = |n: usize| quasi_chemical.calculate_ftc(d(n), t, mu) as f64;
+    let max_of = |f: &dyn Fn(usize) -> f64| {
+        profile
+            .iter()
+            .map(|p| f(p.0))
+            .filter(|f| f.is_finite())
+            .fold(f64::NEG_INFINITY, f64::max)
+    };
+    let (mf_max, qc_max) = (max_of(&mean_field), max_of(&qc));
+
+    let mut file = create_output(&args.output)?;
+    writeln!(
+        file,
+        "molecules,density,ln_p,ln_p_per_site,mean_field,quasi_chemical,visits"
+    )?;
+    for &(n, ln_p, visits) in &profile {
+        writeln!(
+            file,
+            "{n},{},{ln_p},{},{},{},{visits}",
+            n as f64 / sites,
+            ln_p / sites,
+            mean_field(n) - mf_max,
+            qc(n) - qc_max
+        )?;
+    }
+    let ln_p: Vec<f64> = profile.iter().map(|p| p.1).collect();
+    match multicanonical::barrier(&ln_p) {
+        Some(b) => eprintln!(
+            "[ps_cli] multicanonical: barrier βΔF = {b:.3} ({:.3e} per site)",
+            b / sites
+        ),
+        None => eprintln!("[ps_cli] multicanonical: single-peaked profile, no barrier"),
+    }
+    Ok(())
+}
+
+fn phase_boundaries(
+    args: &Args,
+    theory: &dyn Theory,
+    t_min: f64,
+    t_points: usize,
+) -> std::io::Result<()> {
+    let Some(critical) = theory.critical_point() else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "{}: no phase separation for J = {}, α = {}",
+                theory.name(),
+                args.interaction,
+                args.alpha
+            ),
+        ));
+    };
+    eprintln!(
+        "[ps_cli] {} critical point: T_c = {}, µ_c = {}, ρ_c = {}",
+        theory.name(),
+        critical.temperature,
+        critical.chem_potential,
+        critical.density
+    );
+
+    let mut file = create_output(&args.output)?;
+    writeln!(
+        file,
+        "temperature,binodal_mu,binodal_low,binodal_high,spinodal_low,spinodal_high,spinodal_mu_low,spinodal_mu_high"
+    )?;
+    let t_points = t_points.max(1);
+    for k in 0..t_points {
+        let temp = t_min + (critical.temperature - t_min) * k as f64 / t_points as f64;
+        let Some((bin_low, bin_high)) = theory.binodal(temp) else {
+            continue;
+        };
+        let spinodal = theory
+            .spinodal(temp)
+            .map(|(low, high)| {
+                format!(
+                    "{},{},{},{}",
+                    low.density, high.density, low.chem_potential, high.chem_potential
+                )
+            })
+            .unwrap_or_else(|| ",,,".into());
+        writeln!(
+            file,
+            "{temp},{},{},{},{spinodal}",
+            bin_low.chem_potential, bin_low.density, bin_high.density
+        )?;
+    }
+    let (t, mu, rho) = (
+        critical.temperature,
+        critical.chem_potential,
+        critical.density,
+    );
+    if theory.spinodal(t_min).is_some() {
+        writeln!(file, "{t},{mu},{rho},{rho},{rho},{rho},{mu},{mu}")?;
+    } else {
+        writeln!(file, "{t},{mu},{rho},{rho},,,,")?;
+    }
+    Ok(())
+}
+
+/// Distinct sweeps `1..=steps` spaced evenly in `ln t`.
+fn log_spaced_sweeps(steps: u64, points: usize) -> Vec<u64> {
+    let max = (steps.max(1) as f64).ln();
+    let mut sweeps: Vec<u64> = (0..points.max(1))
+        .map(|k| {
+            let frac = if points > 1 {
+                k as f64 / (points - 1) as f64
+            } else {
+                1.0
+            };
+            (frac * max).exp().round() as u64
+        })
+        .map(|t| t.clamp(1, steps.max(1)))
+        .collect();
+    sweeps.dedup();
+    sweeps
+}
+
+/// Least-squares slope of `ln y` against `ln t`.
+fn growth_exponent(points: &[(u64, f64)]) -> Option<f64> {
+    let logs: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|&&(_, y)| y > 0.0)
+        .map(|&(t, y)| ((t as f64).ln(), y.ln()))
+        .collect();
+    if logs.len() < 2 {
+        return None;
+    }
+    let n = logs.len() as f64;
+    let mean_t = logs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = logs.iter().map(|p| p.1).sum::<f64>() / n;
+    let cov: f64 = logs.iter().map(|p| (p.0 - mean_t) * (p.1 - mean_y)).sum();
+    let var: f64 = logs.iter().map(|p| (p.0 - mean_t).powi(2)).sum();
+    (var > 0.0).then(|| cov / var)
+}
+
+fn quench(
+    args: &Args,
+    dynamics: Dynamics,
+    log_points: usize,
+    replicas: usize,
+    q_max: f64,
+) -> std::io::Result<()> {
+    let sweeps = log_spaced_sweeps(args.steps, log_points);
+    let replicas = replicas.max(1);
+    let mut density = vec![0.0; sweeps.len()];
+    let mut interface = vec![0.0; sweeps.len()];
+    let mut structure: Vec<StructureFactor> = sweeps
+        .iter()
+        .map(|_| StructureFactor::new(args.width, args.height))
+        .collect();
+
+    let n_sites = (args.width * args.height) as f64;
+    for replica in 0..replicas {
+        let mut lattice = new_lattice(args);
+        let mut next = 0;
+        for sweep in 1..=args.steps {
+            match dynamics {
+                Dynamics::Metropolis => lattice.step(args.temperature, args.chem_potential),
+                Dynamics::Kawasaki => lattice.step_kawasaki(args.temperature),
+            };
+            if next < sweeps.len() && sweeps[next] == sweep {
+                density[next] += lattice.molecule_count() as f64 / n_sites;
+                interface[next] += lattice.interface_density();
+                structure[next].accumulate(&lattice);
+                next += 1;
+            }
+        }
+        eprintln!("[ps_cli] quench: replica {}/{replicas} done", replica + 1);
+    }
+
+    let r = replicas as f64;
+    let mut file = create_output(&args.output)?;
+    writeln!(
+        file,
+        "sweep,density,interface_density,domain_size_interface,domain_size_sq"
+    )?;
+    let mut sizes_interface = Vec::new();
+    let mut sizes_sq = Vec::new();
+    for (k, &sweep) in sweeps.iter().enumerate() {
+        let rho_i = interface[k] / r;
+        let size_interface = if rho_i > 0.0 {
+            1.0 / (2.0 * rho_i)
+        } else {
+            f64::NAN
+        };
+        let size_sq = structure[k].domain_size(q_max).unwrap_or(f64::NAN);
+        writeln!(
+            file,
+            "{sweep},{},{rho_i},{size_interface},{size_sq}",
+            density[k] / r
+        )?;
+        sizes_interface.push((sweep, size_interface));
+        sizes_sq.push((sweep, size_sq));
+    }
+
+    // Report the growth exponent over the late-time half of the curve, where
+    // the initial transient has died out.
+    let late = sweeps.len() / 2;
+    let fmt = |e: Option<f64>| e.map(|v| format!("{v:.3}")).unwrap_or_else(|| "n/a".into());
+    eprintln!(
+        "[ps_cli] growth exponent (late half): interface {}, S(q) {}",
+        fmt(growth_exponent(&sizes_interface[late..])),
+        fmt(growth_exponent(&sizes_sq[late..]))
+    );
+    Ok(())
+}
+
+fn create_output(path: &str) -> std::io::Result<std::fs::File> {
+    if let Some(dir) = std::path::Path::new(path).parent()
+        && !dir.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::File::create(path)
+}
+
+fn save_correlation_csv(correlation: &PairCorrelation, path: &str) -> std::io::Result<()> {
+    if correlation.samples() == 0 {
+        eprintln!("[ps_cli] warning: no measurement sweeps, correlation function is empty");
+    }
+    let xi = correlation.correlation_length();
+    match xi {
+        Some(xi) => eprintln!(
+            "[ps_cli] correlation length: {xi:.4} ({} samples)",
+            correlation.samples()
+        ),
+        None => eprintln!("[ps_cli] correlation length: fit failed"),
+    }
+
+    let radial = correlation.radial_profile();
+    let (along_x, along_y) = correlation.axis_profiles();
+    let rows = radial.len().max(along_x.len()).max(along_y.len());
+    let cell = |v: &[f64], r: usize| v.get(r).map(|g| g.to_string()).unwrap_or_default();
+
+    let mut file = create_output(path)?;
+    writeln!(
+        file,
+        "# correlation_length={},samples={}",
+        xi.map(|v| v.to_string()).unwrap_or_else(|| "nan".into()),
+        correlation.samples()
+    )?;
+    writeln!(file, "r,radial,along_x,along_y")?;
+    for r in 0..rows {
+        writeln!(
+            file,
+            "{r},{},{},{}",
+            cell(&radial, r),
+            cell(&along_x, r),
+            cell(&along_y, r)
+        )?;
+    }
+    Ok(())
+}
+
+fn save_structure_factor_csv(sf: &StructureFactor, path: &str) -> std::io::Result<()> {
+    let mut file = create_output(path)?;
+    writeln!(file, "q,s_q,modes")?;
+    for (q, s, modes) in sf.radial_profile() {
+        writeln!(file, "{q},{s},{modes}")?;
+    }
+    Ok(())
+}
+
+fn save_structure_factor_map_csv(sf: &StructureFactor, path: &str) -> std::io::Result<()> {
+    let values = sf.values();
+    let mut file = create_output(path)?;
+    writeln!(file, "qx,qy,s_q")?;
+    for ix in 0..sf.width() {
+        for iy in 0..sf.height() {
+            let (qx, qy) = sf.wave_vector(ix, iy);
+            writeln!(file, "{qx},{qy},{}", values[ix * sf.height() + iy])?;
+        }
+    }
+    Ok(())
+}
+
+fn write_interface_row(
+    file: &mut std::fs::File,
+    step: u64,
+    lattice: &Lattice,
+) -> std::io::Result<()> {
+    let clusters = lattice.clusters(clusters::Boundary::Periodic);
+    let (largest_size, largest_perimeter, dimension) = match clusters.largest() {
+        Some(id) => (
+            clusters.clusters[id].size,
+            clusters.clusters[id].perimeter,
+            lattice.boundary_fractal_dimension(&clusters, id),
+        ),
+        None => (0, 0, None),
+    };
+    let mean_perimeter = if clusters.clusters.is_empty() {
+        0.0
+    } else {
+        clusters.clusters.iter().map(|c| c.perimeter).sum::<usize>() as f64
+            / clusters.clusters.len() as f64
+    };
+    writeln!(
+        file,
+        "{step},{},{},{},{largest_size},{largest_perimeter},{mean_perimeter},{}",
+        lattice.interface_length(),
+        lattice.interface_density(),
+        clusters.clusters.len(),
+        dimension.unwrap_or(f64::NAN)
+    )
+}
+
+fn save_clusters_csv(lattice: &Lattice, path: &str) -> std::io::Result<()> {
+    let mut file = create_output(path)?;
+    writeln!(file, "cluster,size,perimeter,wraps_x,wraps_y")?;
+    let clusters = lattice.clusters(clusters::Boundary::Periodic);
+    for (id, c) in clusters.clusters.iter().enumerate() {
+        writeln!(
+            file,
+            "{id},{},{},{},{}",
+            c.size, c.perimeter, c.wraps_x as u8, c.wraps_y as u8
+        )?;
+    }
+    Ok(())
+}
+
+fn save_lattice_snapshot_csv(lattice: &Lattice, path: &str) -> std::io::Result<()> {
+    let mut file = create_output(path)?;
     for y in 0..lattice.height {
         for x in 0..lattice.width {
             let v = if matches!(lattice.grid[x][y], core::Site::Molecule) {
@@ -98,3 +1427,81 @@ fn save_lattice_snapshot_csv(lattice: &Lattice, path: &str) -> std::io::Result<(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> std::io::Result<(Args, Protocol)> {
+        let mut args = Args::parse_from(["ps_cli"]);
+        let protocol = read_protocol(&mut args, text.as_bytes())?;
+        Ok((args, protocol))
+    }
+
+    /// Counts sweeps; its histogram is flat whenever `flat` holds.
+    struct Sampler {
+        sweeps: u64,
+        ln_f: f64,
+        flat: bool,
+    }
+
+    impl FlatHistogram for Sampler {
+        fn sweep(&mut self) {
+            self.sweeps += 1;
+        }
+        fn is_flat(&self) -> bool {
+            self.flat
+        }
+        fn refine(&mut self) {
+            self.ln_f /= 2.0;
+        }
+        fn ln_f(&self) -> f64 {
+            self.ln_f
+        }
+        fn macrostates(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn flatten_refines_at_checks_until_ln_f_is_small() {
+        let mut sampler = Sampler {
+            sweeps: 0,
+            ln_f: 1.0,
+            flat: true,
+        };
+        flatten("test", &mut sampler, 0.1, 10, 1000);
+        assert_eq!((sampler.sweeps, sampler.ln_f), (40, 0.0625));
+
+        let mut sampler = Sampler {
+            sweeps: 0,
+            ln_f: 1.0,
+            flat: false,
+        };
+        flatten("test", &mut sampler, 0.1, 10, 55);
+        assert_eq!((sampler.sweeps, sampler.ln_f), (55, 1.0));
+    }
+
+    #[test]
+    fn protocol_sets_up_the_run() {
+        let (args, protocol) =
+            read("0 set temperature 0.4\n0 rebuild 7 20 10 0.3\n0 set temperature 0.6\n50 end\n")
+                .unwrap();
+        assert_eq!((args.width, args.height, args.steps), (20, 10, 50));
+        assert_eq!(args.temperature, 0.4);
+        assert_eq!(protocol.seed, 7);
+        assert_eq!(protocol.actions.len(), 2);
+    }
+
+    #[test]
+    fn protocol_needs_a_leading_rebuild() {
+        assert!(read("0 set temperature 0.4\n10 end\n").is_err());
+        assert!(read("1 rebuild 7 20 10 0.3\n10 end\n").is_err());
+    }
+
+    #[test]
+    fn protocol_keeps_the_lattice_size() {
+        assert!(read("0 rebuild 7 20 10 0.3\n5 rebuild 8 20 10 0.5\n").is_ok());
+        assert!(read("0 rebuild 7 20 10 0.3\n5 rebuild 8 20 12 0.5\n").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::core::{Lattice, Site};
+use crate::lattice_view::LatticeView;
+use crate::paint::{BrushShape, Stroke};
+use macroquad::prelude::*;
+
+const MAX_RADIUS: i64 = 50;
+
+/// Mouse painting on the lattice view: left button fills sites with
+/// molecules, right button empties them.
+pub struct Brush {
+    pub shape: BrushShape,
+    /// Sites within this distance of the cursor are painted; 0 paints one site.
+    pub radius: i64,
+    /// Site being written by the stroke in progress, and the cursor on the
+    /// previous frame (for a line, where it started).
+    stroke: Option<(Site, (i64, i64))>,
+}
+
+impl Brush {
+    pub fn new() -> Self {
+        Self {
+            shape: BrushShape::Disk,
+            radius: 3,
+            stroke: None,
+        }
+    }
+
+    pub fn cycle_shape(&mut self) {
+        self.shape = match self.shape {
+            BrushShape::Disk => BrushShape::Square,
+            BrushShape::Square => BrushShape::Line,
+            BrushShape::Line => BrushShape::Disk,
+        };
+        self.stroke = None;
+    }
+
+    pub fn resize(&mut self, delta: i64) {
+        self.radius = (self.radius + delta).clamp(0, MAX_RADIUS);
+    }
+
+    /// Applies this frame's mouse input to `lattice`, drawn by `view` in
+    /// `rect`, and returns the stroke painted, if any. A stroke starts with a
+    /// press inside `rect` and lasts until release.
+    pub fn update(
+        &mut self,
+        lattice: &mut Lattice,
+        view: &LatticeView,
+        rect: Rect,
+    ) -> Option<Stroke> {
+        let cursor = view.site_at(lattice, rect, mouse_position().into());
+        let held =
+            is_mouse_button_down(MouseButton::Left) || is_mouse_button_down(MouseButton::Right);
+        let mut painted = None;
+        match (self.stroke, cursor) {
+            (None, Some(p)) => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.stroke = Some((Site::Molecule, p));
+                } else if is_mouse_button_pressed(MouseButton::Right) {
+                    self.stroke = Some((Site::Empty, p));
+                }
+                if self.shape != BrushShape::Line
+                    && let Some((site, _)) = self.stroke
+                {
+                    painted = Some(self.segment(p, p, site));
+                }
+            }
+            (Some((site, from)), _) if !held => {
+                if self.shape == BrushShape::Line
+                    && let Some(to) = cursor
+                {
+                    painted = Some(self.segment(from, to, site));
+                }
+                self.stroke = None;
+            }
+            (Some((site, from)), Some(p)) if self.shape != BrushShape::Line => {
+                // Fill the gap a fast drag leaves between frames.
+                painted = Some(self.segment(from, p, site));
+                self.stroke = Some((site, p));
+            }
+            _ => {}
+        }
+        if let Some(stroke) = &painted {
+            stroke.apply(lattice);
+        }
+        painted
+    }
+
+    /// The segment `from`–`to` with this brush; lines are drawn with the disk.
+    fn segment(&self, from: (i64, i64), to: (i64, i64), site: Site) -> Stroke {
+        let shape = match self.shape {
+            BrushShape::Line => BrushShape::Disk,
+            shape => shape,
+        };
+        Stroke {
+            site,
+            shape,
+            radius: self.radius,
+            from,
+            to,
+        }
+    }
+
+    /// Outline of the brush at the cursor, and the pending segment of a line.
+    pub fn draw_cursor(&self, lattice: &Lattice, view: &LatticeView, rect: Rect) {
+        let Some((x, y)) = view.site_at(lattice, rect, mouse_position().into()) else {
+            return;
+        };
+        let cell = view.cell_size(lattice, rect);
+        let centre = |(x, y): (i64, i64)| {
+            view.to_screen(lattice, rect, vec2(x as f32 + 0.5, y as f32 + 0.5))
+        };
+        let c = centre((x, y));
+        let half_w = (self.radius as f32 + 0.5) * cell.x;
+        let half_h = (self.radius as f32 + 0.5) * cell.y;
+        match self.shape {
+            BrushShape::Square => draw_rectangle_lines(
+                c.x - half_w,
+                c.y - half_h,
+                2.0 * half_w,
+                2.0 * half_h,
+                1.5,
+                ORANGE,
+            ),
+            BrushShape::Disk | BrushShape::Line => draw_circle_lines(c.x, c.y, half_w, 1.5, ORANGE),
+        }
+        if let (BrushShape::Line, Some((_, start))) = (self.shape, self.stroke) {
+            let s = centre(start);
+            draw_line(
+                s.x,
+                s.y,
+                c.x,
+                c.y,
+                (2.0 * half_w).max(1.0),
+                Color::new(1.0, 0.63, 0.0, 0.4),
+            );
+        }
+    }
+}
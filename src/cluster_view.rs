@@ -0,0 +1,154 @@
+use crate::clusters::{Boundary, Clusters, Percolation};
+use crate::core::Lattice;
+use crate::phase_color_dark;
+use macroquad::color::hsl_to_rgb;
+use macroquad::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+fn cluster_color(color_id: u32) -> Color {
+    let hue = (color_id as f32 * 0.618_034).fract();
+    hsl_to_rgb(hue, 0.35, 0.55)
+}
+
+fn spanning_color() -> Color {
+    color_u8!(255, 196, 0, 255)
+}
+
+fn wrapping_color() -> Color {
+    color_u8!(0, 190, 200, 255)
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct ClusterStats {
+    pub count: usize,
+    pub largest: usize,
+    pub largest_perimeter: usize,
+    pub boundary_dimension: Option<f64>,
+    pub percolation: Percolation,
+}
+
+/// Periodic cluster labelling of the lattice with colours that follow clusters
+/// from frame to frame. An open labelling alongside it finds the clusters
+/// that span the lattice between opposite edges.
+pub struct ClusterView {
+    clusters: Clusters,
+    open: Clusters,
+    colors: Vec<Vec<Option<u32>>>,
+    next_color: u32,
+    stats: ClusterStats,
+}
+
+impl ClusterView {
+    pub fn new() -> Self {
+        Self {
+            clusters: Clusters {
+                labels: Vec::new(),
+                clusters: Vec::new(),
+            },
+            open: Clusters {
+                labels: Vec::new(),
+                clusters: Vec::new(),
+            },
+            colors: Vec::new(),
+            next_color: 0,
+            stats: ClusterStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &ClusterStats {
+        &self.stats
+    }
+
+    pub fn update(&mut self, lattice: &Lattice) {
+        let (w, h) = (lattice.width, lattice.height);
+        let clusters = lattice.clusters(Boundary::Periodic);
+        let open = lattice.clusters(Boundary::Open);
+        if self.colors.len() != w || self.colors.first().is_none_or(|col| col.len() != h) {
+            self.colors = vec![vec![None; h]; w];
+        }
+
+        // A cluster inherits the colour it overlaps most with from the previous
+        // frame; each colour goes to at most one cluster, largest overlap first,
+        // so after a split only the bigger fragment keeps it.
+        let mut overlap: HashMap<(usize, u32), usize> = HashMap::new();
+        for x in 0..w {
+            for y in 0..h {
+                if let (Some(id), Some(color)) = (clusters.labels[x][y], self.colors[x][y]) {
+                    *overlap.entry((id, color)).or_default() += 1;
+                }
+            }
+        }
+        let mut candidates: Vec<_> = overlap.into_iter().collect();
+        candidates.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+        let mut assigned: Vec<Option<u32>> = vec![None; clusters.clusters.len()];
+        let mut taken = HashSet::new();
+        for ((id, color), _) in candidates {
+            if assigned[id].is_none() && taken.insert(color) {
+                assigned[id] = Some(color);
+            }
+        }
+        let assigned: Vec<u32> = assigned
+            .into_iter()
+            .map(|color| {
+                color.unwrap_or_else(|| {
+                    self.next_color += 1;
+                    self.next_color
+                })
+            })
+            .collect();
+
+        for x in 0..w {
+            for y in 0..h {
+                self.colors[x][y] = clusters.labels[x][y].map(|id| assigned[id]);
+            }
+        }
+
+        let largest = clusters.largest();
+        self.stats = ClusterStats {
+            count: clusters.clusters.len(),
+            largest: clusters.largest_size(),
+            largest_perimeter: largest.map_or(0, |id| clusters.clusters[id].perimeter),
+            boundary_dimension: largest
+                .and_then(|id| lattice.boundary_fractal_dimension(&clusters, id)),
+            percolation: Percolation::of(&open, &clusters),
+        };
+        self.clusters = clusters;
+        self.open = open;
+    }
+
+    /// Colour of site `(x, y)`: every cluster in its own muted colour, with
+    /// clusters that span the lattice between opposite edges highlighted and
+    /// those that only wrap around the torus marked in a second colour.
+    pub fn site_color(&self, x: usize, y: usize) -> Color {
+        let label_at = |clusters: &Clusters| {
+            clusters
+                .labels
+                .get(x)
+                .and_then(|col| col.get(y))
+                .copied()
+                .flatten()
+        };
+        let spans = label_at(&self.open).is_some_and(|id| {
+            let c = &self.open.clusters[id];
+            c.spans_x || c.spans_y
+        });
+        let label = label_at(&self.clusters);
+        let color_id = self
+            .colors
+            .get(x)
+            .and_then(|col| col.get(y))
+            .copied()
+            .flatten();
+        match (label, color_id) {
+            (Some(_), _) if spans => spanning_color(),
+            (Some(id), _)
+                if self.clusters.clusters[id].wraps_x || self.clusters.clusters[id].wraps_y =>
+            {
+                wrapping_color()
+            }
+            (Some(_), Some(color_id)) => cluster_color(color_id),
+            _ => phase_color_dark(),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::core::{Lattice, Site};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Boundary {
+    /// Bonds across the lattice edges are cut, as between the electrodes of a device.
+    Open,
+    /// Bonds wrap around the torus, as in `Lattice::step`.
+    Periodic,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Cluster {
+    pub size: usize,
+    /// Occupied–empty bonds of the cluster, including those facing enclosed holes.
+    pub perimeter: usize,
+    /// Touches both the left and right edge (open labelling only).
+    pub spans_x: bool,
+    /// Touches both the top and bottom edge (open labelling only).
+    pub spans_y: bool,
+    /// Winds around the torus horizontally (periodic labelling only).
+    pub wraps_x: bool,
+    /// Winds around the torus vertically (periodic labelling only).
+    pub wraps_y: bool,
+}
+
+pub struct Clusters {
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub clusters: Vec<Cluster>,
+}
+
+impl Clusters {
+    pub fn largest(&self) -> Option<usize> {
+        (0..self.clusters.len()).max_by_key(|&id| self.clusters[id].size)
+    }
+
+    pub fn largest_size(&self) -> usize {
+        self.clusters.iter().map(|c| c.size).max().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Percolation {
+    pub spans_x: bool,
+    pub spans_y: bool,
+    pub wraps_x: bool,
+    pub wraps_y: bool,
+}
+
+impl Percolation {
+    /// Spanning flags from an open labelling and winding flags from a
+    /// periodic labelling of the same lattice.
+    pub fn of(open: &Clusters, periodic: &Clusters) -> Self {
+        let mut result = Percolation::default();
+        for c in &open.clusters {
+            result.spans_x |= c.spans_x;
+            result.spans_y |= c.spans_y;
+        }
+        for c in &periodic.clusters {
+            result.wraps_x |= c.wraps_x;
+            result.wraps_y |= c.wraps_y;
+        }
+        result
+    }
+}
+
+impl Lattice {
+    /// Labels nearest-neighbour molecule clusters.
+    ///
+    /// Sites are visited depth-first while tracking their unwrapped position;
+    /// reaching an already labelled site at a different unwrapped position means
+    /// the cluster winds around the torus in that direction.
+    pub fn clusters(&self, boundary: Boundary) -> Clusters {
+        let (w, h) = (self.width, self.height);
+        let mut labels = vec![vec![None; h]; w];
+        let mut unwrapped = vec![vec![(0i64, 0i64); h]; w];
+        let mut clusters = Vec::new();
+        let mut stack = Vec::new();
+
+        for sx in 0..w {
+            for sy in 0..h {
+                if self.grid[sx][sy] != Site::Molecule || labels[sx][sy].is_some() {
+                    continue;
+                }
+                let id = clusters.len();
+                let mut cluster = Cluster::default();
+                let (mut left, mut right, mut top, mut bottom) = (false, false, false, false);
+
+                labels[sx][sy] = Some(id);
+                unwrapped[sx][sy] = (sx as i64, sy as i64);
+                stack.push((sx, sy));
+
+                while let Some((x, y)) = stack.pop() {
+                    cluster.size += 1;
+                    left |= x == 0;
+                    right |= x + 1 == w;
+                    top |= y == 0;
+                    bottom |= y + 1 == h;
+
+                    let (ux, uy) = unwrapped[x][y];
+                    for (dx, dy) in [(1i64, 0i64), (-1, 0), (0, 1), (0, -1)] {
+                        let nx = x as i64 + dx;
+                        let ny = y as i64 + dy;
+                        let outside = nx < 0 || ny < 0 || nx >= w as i64 || ny >= h as i64;
+                        if outside && boundary == Boundary::Open {
+                            continue;
+                        }
+                        let nx = nx.rem_euclid(w as i64) as usize;
+                        let ny = ny.rem_euclid(h as i64) as usize;
+                        if self.grid[nx][ny] != Site::Molecule {
+                            cluster.perimeter += 1;
+                            continue;
+                        }
+                        let target = (ux + dx, uy + dy);
+                        match labels[nx][ny] {
+                            None => {
+                                labels[nx][ny] = Some(id);
+                                unwrapped[nx][ny] = target;
+                                stack.push((nx, ny));
+                            }
+                            Some(_) => {
+                                let seen = unwrapped[nx][ny];
+                                cluster.wraps_x |= seen.0 != target.0;
+                                cluster.wraps_y |= seen.1 != target.1;
+                            }
+                        }
+                    }
+                }
+
+                if boundary == Boundary::Open {
+                    cluster.spans_x = left && right;
+                    cluster.spans_y = top && bottom;
+                }
+                clusters.push(cluster);
+            }
+        }
+
+        Clusters { labels, clusters }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `w × h` lattice with molecules at `sites` only.
+    fn lattice(w: usize, h: usize, sites: &[(usize, usize)]) -> Lattice {
+        let mut lattice = Lattice::new_with_params(w, h, 1.0, 0.0, 0.0, 0.0);
+        for &(x, y) in sites {
+            lattice.grid[x][y] = Site::Molecule;
+        }
+        lattice
+    }
+
+    fn flags(c: &Cluster) -> [bool; 4] {
+        [c.spans_x, c.spans_y, c.wraps_x, c.wraps_y]
+    }
+
+    #[test]
+    fn full_row_spans_and_wraps_in_x_only() {
+        let row: Vec<_> = (0..6).map(|x| (x, 2)).collect();
+        let lattice = lattice(6, 5, &row);
+        let open = lattice.clusters(Boundary::Open);
+        let periodic = lattice.clusters(Boundary::Periodic);
+        assert_eq!(open.clusters.len(), 1);
+        assert_eq!(flags(&open.clusters[0]), [true, false, false, false]);
+        assert_eq!(periodic.clusters.len(), 1);
+        assert_eq!(flags(&periodic.clusters[0]), [false, false, true, false]);
+    }
+
+    #[test]
+    fn full_column_spans_and_wraps_in_y_only() {
+        let column: Vec<_> = (0..5).map(|y| (3, y)).collect();
+        let lattice = lattice(6, 5, &column);
+        let open = lattice.clusters(Boundary::Open);
+        let periodic = lattice.clusters(Boundary::Periodic);
+        assert_eq!(flags(&open.clusters[0]), [false, true, false, false]);
+        assert_eq!(flags(&periodic.clusters[0]), [false, false, false, true]);
+    }
+
+    #[test]
+    fn ring_through_the_corner_joins_only_periodically_and_does_not_wrap() {
+        // The eight sites around (0, 0), connected only across both edges.
+        let ring = [
+            (5, 5),
+            (0, 5),
+            (1, 5),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (5, 1),
+            (5, 0),
+        ];
+        let lattice = lattice(6, 6, &ring);
+        let open = lattice.clusters(Boundary::Open);
+        assert_eq!(open.clusters.len(), 4);
+        assert!(open.clusters.iter().all(|c| flags(c) == [false; 4]));
+        let periodic = lattice.clusters(Boundary::Periodic);
+        assert_eq!(periodic.clusters.len(), 1);
+        assert_eq!(periodic.clusters[0].size, 8);
+        assert_eq!(flags(&periodic.clusters[0]), [false; 4]);
+    }
+
+    #[test]
+    fn diagonal_staircase_across_the_edges_does_not_wrap() {
+        let stairs = [
+            (6, 6),
+            (7, 6),
+            (7, 7),
+            (0, 7),
+            (0, 0),
+            (1, 0),
+            (1, 1),
+            (2, 1),
+        ];
+        let lattice = lattice(8, 8, &stairs);
+        let periodic = lattice.clusters(Boundary::Periodic);
+        assert_eq!(periodic.clusters.len(), 1);
+        assert_eq!(flags(&periodic.clusters[0]), [false; 4]);
+        let open = lattice.clusters(Boundary::Open);
+        assert_eq!(open.clusters.len(), 3);
+        assert!(open.clusters.iter().all(|c| flags(c) == [false; 4]));
+    }
+
+    #[test]
+    fn empty_lattice_has_no_clusters() {
+        let lattice = lattice(4, 4, &[]);
+        for boundary in [Boundary::Open, Boundary::Periodic] {
+            let clusters = lattice.clusters(boundary);
+            assert!(clusters.clusters.is_empty());
+            assert_eq!(clusters.largest(), None);
+            assert_eq!(clusters.largest_size(), 0);
+        }
+    }
+}
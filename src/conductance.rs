@@ -0,0 +1,183 @@
+use crate::core::{Lattice, Site};
+
+const MAX_ITERATIONS: usize = 200;
+const TOLERANCE: f64 = 1e-6;
+
+/// Effective conductance of the lattice as a resistor network, as in the
+/// validation notebook: unit bonds between neighbouring molecules, electrodes
+/// at `V = 1` on column 0 and `V = 0` on the last column, periodic in `y`.
+///
+/// Kirchhoff's equations are solved by Jacobi-preconditioned conjugate
+/// gradients warm-started from the previous voltages, with a fixed number of
+/// iterations per call, so a slowly changing lattice stays cheap to follow.
+pub struct Conductance {
+    size: (usize, usize),
+    /// Voltages of the inner columns, indexed `(x - 1) * height + y`.
+    voltage: Vec<f64>,
+}
+
+impl Conductance {
+    pub fn new() -> Self {
+        Self {
+            size: (0, 0),
+            voltage: Vec::new(),
+        }
+    }
+
+    /// `G / G_max`, where `G_max = height / (width − 1)` is the conductance
+    /// of the fully occupied lattice.
+    pub fn measure(&mut self, lattice: &Lattice) -> f64 {
+        let (w, h) = (lattice.width, lattice.height);
+        if w < 2 || h == 0 {
+            return 0.0;
+        }
+        let occupied = |x: usize, y: usize| lattice.grid[x][y] == Site::Molecule;
+        let inner = w - 2;
+        if self.size != (w, h) {
+            self.size = (w, h);
+            self.voltage = (0..inner * h)
+                .map(|i| 1.0 - (i / h + 1) as f64 / (w - 1) as f64)
+                .collect();
+        }
+
+        // Bonds between inner sites, and each site's bond count and current
+        // source from the left electrode.
+        let mut links = Vec::new();
+        let mut diag = vec![0.0; inner * h];
+        let mut source = vec![0.0; inner * h];
+        for x in 1..w - 1 {
+            for y in 0..h {
+                if !occupied(x, y) {
+                    continue;
+                }
+                let i = (x - 1) * h + y;
+                for (nx, ny) in [
+                    (x - 1, y),
+                    (x + 1, y),
+                    (x, (y + 1) % h),
+                    (x, (y + h - 1) % h),
+                ] {
+                    if (nx, ny) == (x, y) || !occupied(nx, ny) {
+                        continue;
+                    }
+                    diag[i] += 1.0;
+                    if nx == 0 {
+                        source[i] += 1.0;
+                    } else if nx + 1 < w {
+                        let j = (nx - 1) * h + ny;
+                        if i < j {
+                            links.push((i, j));
+                        }
+                    }
+                }
+            }
+        }
+        // Sites without bonds carry no current; pin them to zero.
+        for (v, &d) in self.voltage.iter_mut().zip(&diag) {
+            if d == 0.0 {
+                *v = 0.0;
+            }
+        }
+        solve(&links, &diag, &source, &mut self.voltage);
+
+        let current: f64 = (0..h)
+            .filter(|&y| occupied(0, y) && occupied(1, y))
+            .map(|y| 1.0 - if w > 2 { self.voltage[y] } else { 0.0 })
+            .sum();
+        current * (w - 1) as f64 / h as f64
+    }
+}
+
+/// `out = A v` for the conductance matrix with diagonal `diag` and −1 for
+/// every link.
+fn apply(links: &[(usize, usize)], diag: &[f64], v: &[f64], out: &mut [f64]) {
+    for ((o, &d), &vi) in out.iter_mut().zip(diag).zip(v) {
+        *o = d * vi;
+    }
+    for &(i, j) in links {
+        out[i] -= v[j];
+        out[j] -= v[i];
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn solve(links: &[(usize, usize)], diag: &[f64], b: &[f64], v: &mut [f64]) {
+    let n = v.len();
+    let precondition = |r: &[f64], z: &mut [f64]| {
+        for ((z, &r), &d) in z.iter_mut().zip(r).zip(diag) {
+            *z = if d > 0.0 { r / d } else { 0.0 };
+        }
+    };
+    let mut r = vec![0.0; n];
+    apply(links, diag, v, &mut r);
+    for (r, &b) in r.iter_mut().zip(b) {
+        *r = b - *r;
+    }
+    let mut z = vec![0.0; n];
+    precondition(&r, &mut z);
+    let mut p = z.clone();
+    let mut rz = dot(&r, &z);
+    let mut ap = vec![0.0; n];
+    let threshold = TOLERANCE * TOLERANCE * dot(b, b).max(1.0);
+    for _ in 0..MAX_ITERATIONS {
+        if dot(&r, &r) <= threshold {
+            break;
+        }
+        apply(links, diag, &p, &mut ap);
+        let pap = dot(&p, &ap);
+        if pap <= 0.0 {
+            break;
+        }
+        let step = rz / pap;
+        for (v, &p) in v.iter_mut().zip(&p) {
+            *v += step * p;
+        }
+        for (r, &ap) in r.iter_mut().zip(&ap) {
+            *r -= step * ap;
+        }
+        precondition(&r, &mut z);
+        let rz_next = dot(&r, &z);
+        let beta = rz_next / rz;
+        rz = rz_next;
+        for (p, &z) in p.iter_mut().zip(&z) {
+            *p = z + beta * *p;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `w × h` lattice with molecules on the given rows.
+    fn lattice(w: usize, h: usize, rows: &[usize]) -> Lattice {
+        let mut lattice = Lattice::new_with_params(w, h, 1.0, 0.0, 0.0, 0.0);
+        for x in 0..w {
+            for &y in rows {
+                lattice.grid[x][y] = Site::Molecule;
+            }
+        }
+        lattice
+    }
+
+    #[test]
+    fn full_and_empty_lattices() {
+        let all: Vec<usize> = (0..6).collect();
+        assert!((Conductance::new().measure(&lattice(10, 6, &all)) - 1.0).abs() < 1e-6);
+        assert_eq!(Conductance::new().measure(&lattice(10, 6, &[])), 0.0);
+    }
+
+    #[test]
+    fn straight_channels_conduct_in_parallel() {
+        let mut conductance = Conductance::new();
+        let one = conductance.measure(&lattice(10, 6, &[2]));
+        assert!((one - 1.0 / 6.0).abs() < 1e-6, "{one}");
+        let two = conductance.measure(&lattice(10, 6, &[1, 4]));
+        assert!((two - 2.0 / 6.0).abs() < 1e-6, "{two}");
+        let adjacent = conductance.measure(&lattice(10, 6, &[4, 5]));
+        assert!((adjacent - 2.0 / 6.0).abs() < 1e-6, "{adjacent}");
+    }
+}
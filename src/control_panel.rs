@@ -0,0 +1,238 @@
+use macroquad::prelude::*;
+use macroquad::ui::{Id, hash, root_ui, widgets};
+use std::path::{Path, PathBuf};
+
+/// Limits of the sweep rate, for the slider and the speed keys alike.
+const SWEEPS_PER_FRAME: (f32, f32) = (1.0 / 64.0, 64.0);
+
+/// Model and run parameters the GUI can change while running.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub temperature: f32,
+    pub chem_potential: f32,
+    pub j: f32,
+    pub epsilon0: f32,
+    pub alpha: f32,
+    pub width: usize,
+    pub height: usize,
+    pub init_density: f32,
+    /// Fractional rates run one sweep every few frames.
+    pub sweeps_per_frame: f32,
+    /// Change of T per frame while `↑` or `↓` is held.
+    pub temperature_step: f32,
+    /// Change of µ per frame while `→` or `←` is held.
+    pub chem_potential_step: f32,
+    pub paused: bool,
+}
+
+impl Settings {
+    /// Doubles (`factor = 2`) or halves the sweep rate within
+    /// `SWEEPS_PER_FRAME`.
+    pub fn scale_speed(&mut self, factor: f32) {
+        let (min, max) = SWEEPS_PER_FRAME;
+        self.sweeps_per_frame = (self.sweeps_per_frame * factor).clamp(min, max);
+    }
+}
+
+/// Turns the sweeps-per-frame rate into whole sweeps, carrying the fraction
+/// over so that a rate of 0.25 runs one sweep every fourth frame.
+pub struct Pacer {
+    budget: f32,
+    single_steps: usize,
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Self {
+            budget: 0.0,
+            single_steps: 0,
+        }
+    }
+
+    /// Queues one sweep for the next frame, run even while paused.
+    pub fn single_step(&mut self) {
+        self.single_steps += 1;
+    }
+
+    pub fn sweeps(&mut self, settings: &Settings) -> usize {
+        let mut sweeps = std::mem::take(&mut self.single_steps);
+        if !settings.paused {
+            self.budget += settings.sweeps_per_frame;
+            let whole = self.budget.floor();
+            self.budget -= whole;
+            sweeps += whole as usize;
+        }
+        sweeps
+    }
+}
+
+const SLIDERS: [&str; 9] = [
+    "T",
+    "µ",
+    "J",
+    "ε0",
+    "α",
+    "init ρ",
+    "sweeps/frame",
+    "T step",
+    "µ step",
+];
+const MAX_SIZE: usize = 1000;
+
+/// Window with sliders (each with a numeric entry box) for the model
+/// parameters and text fields for the lattice size, the directory that
+/// exports are written to and the session file to replay.
+pub struct ControlPanel {
+    visible: bool,
+    width_text: String,
+    height_text: String,
+    output_dir: String,
+    session: String,
+    error: Option<String>,
+}
+
+impl ControlPanel {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            visible: true,
+            width_text: settings.width.to_string(),
+            height_text: settings.height.to_string(),
+            output_dir: ".".to_string(),
+            session: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Directory for CSV, PNG and GIF exports, created on the first export.
+    pub fn output_dir(&self) -> PathBuf {
+        PathBuf::from(self.output_dir.trim())
+    }
+
+    pub fn session_path(&self) -> PathBuf {
+        PathBuf::from(self.session.trim())
+    }
+
+    pub fn set_session_path(&mut self, path: &Path) {
+        self.session = path.display().to_string();
+    }
+
+    /// Hotkeys and clicks on the panels below are ignored while this holds,
+    /// so that typing a value does not also trigger them.
+    pub fn is_mouse_over(&self) -> bool {
+        self.visible && root_ui().is_mouse_over(mouse_position().into())
+    }
+
+    /// Draws the panel at `position` (only on first show; the user may drag
+    /// it afterwards) and applies edits to `settings`. Returns whether the
+    /// lattice must be rebuilt with a new size and initial density.
+    ///
+    /// While `locked` (a session is replaying) the model parameters, size
+    /// and initial density are shown but edits to them are discarded, so the
+    /// replay keeps matching its recording; pacing can still be changed.
+    pub fn ui(
+        &mut self,
+        position: Vec2,
+        settings: &mut Settings,
+        pacer: &mut Pacer,
+        locked: bool,
+    ) -> bool {
+        let mut rebuild = false;
+        if !self.visible {
+            return false;
+        }
+        let before = *settings;
+        widgets::Window::new(hash!(), position, vec2(330.0, 460.0))
+            .label("Parameters")
+            .ui(&mut root_ui(), |ui| {
+                ui.slider(id(0), SLIDERS[0], 0.01..3.0, &mut settings.temperature);
+                ui.slider(id(1), SLIDERS[1], -4.0..2.0, &mut settings.chem_potential);
+                ui.slider(id(2), SLIDERS[2], -1.0..2.0, &mut settings.j);
+                ui.slider(id(3), SLIDERS[3], -2.0..2.0, &mut settings.epsilon0);
+                ui.slider(id(4), SLIDERS[4], -1.0..2.0, &mut settings.alpha);
+                ui.slider(id(5), SLIDERS[5], 0.0..1.0, &mut settings.init_density);
+                ui.slider(
+                    id(6),
+                    SLIDERS[6],
+                    SWEEPS_PER_FRAME.0..SWEEPS_PER_FRAME.1,
+                    &mut settings.sweeps_per_frame,
+                );
+                ui.slider(
+                    id(7),
+                    SLIDERS[7],
+                    0.001..0.2,
+                    &mut settings.temperature_step,
+                );
+                ui.slider(
+                    id(8),
+                    SLIDERS[8],
+                    0.001..0.5,
+                    &mut settings.chem_potential_step,
+                );
+                ui.checkbox(hash!("paused"), "paused", &mut settings.paused);
+                ui.same_line(0.0);
+                if ui.button(None, "step") {
+                    settings.paused = true;
+                    pacer.single_step();
+                }
+                ui.separator();
+                widgets::InputText::new(hash!("width"))
+                    .label("width")
+                    .filter_numbers()
+                    .ui(ui, &mut self.width_text);
+                widgets::InputText::new(hash!("height"))
+                    .label("height")
+                    .filter_numbers()
+                    .ui(ui, &mut self.height_text);
+                if ui.button(None, "Apply size / rebuild") {
+                    match (parse_size(&self.width_text), parse_size(&self.height_text)) {
+                        (Some(width), Some(height)) => {
+                            settings.width = width;
+                            settings.height = height;
+                            self.error = None;
+                            rebuild = true;
+                        }
+                        _ => self.error = Some(format!("size must be 2..={MAX_SIZE}")),
+                    }
+                }
+                if let Some(error) = &self.error {
+                    ui.label(None, error);
+                }
+                ui.separator();
+                widgets::InputText::new(hash!("output dir"))
+                    .label("output dir")
+                    .ui(ui, &mut self.output_dir);
+                widgets::InputText::new(hash!("session"))
+                    .label("session")
+                    .ui(ui, &mut self.session);
+                if locked {
+                    ui.label(None, "replaying: model parameters locked");
+                }
+            });
+        if locked {
+            *settings = Settings {
+                sweeps_per_frame: settings.sweeps_per_frame,
+                temperature_step: settings.temperature_step,
+                chem_potential_step: settings.chem_potential_step,
+                paused: settings.paused,
+                ..before
+            };
+            return false;
+        }
+        rebuild
+    }
+}
+
+fn id(slider: usize) -> Id {
+    hash!("slider", SLIDERS[slider])
+}
+
+fn parse_size(text: &str) -> Option<usize> {
+    text.trim()
+        .parse()
+        .ok()
+        .filter(|size| (2..=MAX_SIZE).contains(size))
+}
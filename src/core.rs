@@ -1,8 +1,10 @@
 #![allow(clippy::needless_range_loop)]
 
-use ::rand::{Rng, random, rng};
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng, random};
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 
 #[derive(Clone, Copy, PartialEq)]
@@ -19,6 +21,7 @@ pub struct Lattice {
     pub epsilon0: f32,
     pub alpha: f32,
     pub num_molecules: i32,
+    rng: StdRng,
 }
 
 impl Lattice {
@@ -30,11 +33,26 @@ impl Lattice {
         alpha: f32,
         init_density: f32,
     ) -> Self {
+        Self::new_seeded(width, height, j, epsilon0, alpha, init_density, random())
+    }
+
+    /// The initial configuration and every sweep are drawn from an RNG
+    /// seeded with `seed`, so equal seeds and parameters give equal runs.
+    pub fn new_seeded(
+        width: usize,
+        height: usize,
+        j: f32,
+        epsilon0: f32,
+        alpha: f32,
+        init_density: f32,
+        seed: u64,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
         let mut grid = vec![vec![Site::Empty; height]; width];
         let mut count: i32 = 0;
         for x in 0..width {
             for y in 0..height {
-                if random::<f32>() < init_density {
+                if rng.random::<f32>() < init_density {
                     grid[x][y] = Site::Molecule;
                     count += 1;
                 }
@@ -48,6 +66,7 @@ impl Lattice {
             epsilon0,
             alpha,
             num_molecules: count,
+            rng,
         }
     }
 
@@ -55,97 +74,357 @@ impl Lattice {
         self.num_molecules as usize
     }
 
-    pub fn step(&mut self, temp: f32, chem_potential: f32) {
-        if temp <= 0.0 {
-            return;
+    /// The generator behind the lattice's moves, for samplers that make their
+    /// own moves on it.
+    #[allow(dead_code)] // ps_cli only
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Clamps `n_range` to the lattice and fills or empties random sites
+    /// until the molecule count lies in `n_range.0..=n_range.1`. Returns the
+    /// clamped range.
+    #[allow(dead_code)] // ps_cli only
+    pub fn enter_n_range(&mut self, n_range: (usize, usize)) -> (usize, usize) {
+        let (w, h) = (self.width, self.height);
+        let n_range = (n_range.0.min(w * h), n_range.1.clamp(n_range.0, w * h));
+        while !(n_range.0..=n_range.1).contains(&self.molecule_count()) {
+            let (x, y) = (self.rng.random_range(0..w), self.rng.random_range(0..h));
+            if self.molecule_count() < n_range.0 {
+                self.set_site(x, y, Site::Molecule);
+            } else {
+                self.set_site(x, y, Site::Empty);
+            }
+        }
+        n_range
+    }
+
+    /// Sets a single site, keeping `num_molecules` consistent.
+    pub fn set_site(&mut self, x: usize, y: usize, site: Site) {
+        match (self.grid[x][y], site) {
+            (Site::Empty, Site::Molecule) => self.num_molecules += 1,
+            (Site::Molecule, Site::Empty) => self.num_molecules -= 1,
+            _ => {}
+        }
+        self.grid[x][y] = site;
+    }
+
+    /// Site occupations `0/1` flattened as `[x * height + y]`.
+    pub fn occupations(&self) -> Vec<f64> {
+        self.grid
+            .iter()
+            .flatten()
+            .map(|&s| if s == Site::Molecule { 1.0 } else { 0.0 })
+            .collect()
+    }
+
+    /// Number of occupied–occupied nearest-neighbour bonds.
+    pub fn bond_count(&self) -> usize {
+        let mut count = 0;
+        for x in 0..self.width {
+            let right = (x + 1) % self.width;
+            for y in 0..self.height {
+                if self.grid[x][y] != Site::Molecule {
+                    continue;
+                }
+                let down = (y + 1) % self.height;
+                count += (self.grid[right][y] == Site::Molecule) as usize;
+                count += (self.grid[x][down] == Site::Molecule) as usize;
+            }
         }
-        let mut rng = rng();
-        let mut n_mol: i32 = self.num_molecules;
+        count
+    }
+
+    /// Energy without the `-µN` term; see `model_energy`.
+    pub fn energy(&self) -> f64 {
+        self.energy_of(self.bond_count(), self.molecule_count())
+    }
+
+    /// Energy of a configuration of this lattice with `bonds` occupied bonds
+    /// and `molecules` molecules.
+    pub fn energy_of(&self, bonds: usize, molecules: usize) -> f64 {
+        model_energy(
+            self.j,
+            self.epsilon0,
+            self.alpha,
+            self.width * self.height,
+            bonds,
+            molecules,
+        )
+    }
+
+    /// Change of `H − µN` when site `(x, y)` is flipped.
+    pub fn delta_h(&self, x: usize, y: usize, chem_potential: f32) -> f32 {
         let v_sites: f32 = (self.width * self.height) as f32;
+        let neighbor_molecules = self.neighbor_molecules(x, y);
+
+        let j: f32 = self.j;
+        let delta_e = match self.grid[x][y] {
+            Site::Empty => -j * neighbor_molecules as f32,
+            Site::Molecule => j * neighbor_molecules as f32,
+        };
+        let delta_n_f = match self.grid[x][y] {
+            Site::Empty => 1.0,
+            Site::Molecule => -1.0,
+        };
+
+        let delta_site = self.epsilon0 * delta_n_f
+            - self.alpha * ((2.0 * self.num_molecules as f32 * delta_n_f) + 1.0) / v_sites;
+
+        delta_e + delta_site - chem_potential * delta_n_f
+    }
+
+    pub fn flip(&mut self, x: usize, y: usize) {
+        let site = match self.grid[x][y] {
+            Site::Molecule => Site::Empty,
+            Site::Empty => Site::Molecule,
+        };
+        self.set_site(x, y, site);
+    }
+
+    /// One Metropolis sweep; returns the number of accepted flips.
+    pub fn step(&mut self, temp: f32, chem_potential: f32) -> usize {
+        if temp <= 0.0 {
+            return 0;
+        }
+        let mut accepted = 0;
         for _ in 0..(self.width * self.height) {
-            let x = rng.random_range(0..self.width);
-            let y = rng.random_range(0..self.height);
-            let current_site = self.grid[x][y];
-
-            let mut neighbor_molecules = 0;
-            let up = (y + self.height - 1) % self.height;
-            let down = (y + 1) % self.height;
-            let left = (x + self.width - 1) % self.width;
-            let right = (x + 1) % self.width;
+            let x = self.rng.random_range(0..self.width);
+            let y = self.rng.random_range(0..self.height);
+            let delta_h = self.delta_h(x, y, chem_potential);
 
-            if self.grid[x][up] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-            if self.grid[x][down] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-            if self.grid[left][y] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-            if self.grid[right][y] == Site::Molecule {
-                neighbor_molecules += 1;
+            if delta_h <= 0.0 || self.rng.random::<f32>() < (-delta_h / temp).exp() {
+                self.flip(x, y);
+                accepted += 1;
             }
+        }
+        accepted
+    }
 
-            let j: f32 = self.j;
-            let delta_e = match current_site {
-                Site::Empty => -j * neighbor_molecules as f32,
-                Site::Molecule => j * neighbor_molecules as f32,
-            };
-            let delta_n_f = match current_site {
-                Site::Empty => 1.0,
-                Site::Molecule => -1.0,
+    pub fn neighbor_molecules(&self, x: usize, y: usize) -> i32 {
+        let up = (y + self.height - 1) % self.height;
+        let down = (y + 1) % self.height;
+        let left = (x + self.width - 1) % self.width;
+        let right = (x + 1) % self.width;
+        [
+            self.grid[x][up],
+            self.grid[x][down],
+            self.grid[left][y],
+            self.grid[right][y],
+        ]
+        .iter()
+        .filter(|&&s| s == Site::Molecule)
+        .count() as i32
+    }
+
+    /// Change of the energy when the molecule at `from` hops to the empty
+    /// neighbouring site `to`.
+    fn hop_delta_e(&self, (fx, fy): (usize, usize), (tx, ty): (usize, usize)) -> f32 {
+        // The target's neighbour count includes the hopping molecule itself.
+        self.j * (self.neighbor_molecules(fx, fy) - self.neighbor_molecules(tx, ty) + 1) as f32
+    }
+
+    /// One sweep of Kawasaki dynamics: a molecule hops to an empty
+    /// nearest-neighbour site, so `num_molecules` is conserved and only the
+    /// coupling `j` enters the Metropolis test. Returns the number of hops.
+    #[allow(dead_code)] // ps_cli only
+    pub fn step_kawasaki(&mut self, temp: f32) -> usize {
+        if temp <= 0.0 {
+            return 0;
+        }
+        let mut accepted = 0;
+        for _ in 0..(self.width * self.height) {
+            let x = self.rng.random_range(0..self.width);
+            let y = self.rng.random_range(0..self.height);
+            let (nx, ny) = match self.rng.random_range(0..4) {
+                0 => (x, (y + self.height - 1) % self.height),
+                1 => (x, (y + 1) % self.height),
+                2 => ((x + self.width - 1) % self.width, y),
+                _ => ((x + 1) % self.width, y),
             };
-            let delta_n_i = if matches!(current_site, Site::Empty) {
-                1
+            if self.grid[x][y] == self.grid[nx][ny] {
+                continue;
+            }
+            let ((fx, fy), (tx, ty)) = if self.grid[x][y] == Site::Molecule {
+                ((x, y), (nx, ny))
             } else {
-                -1
+                ((nx, ny), (x, y))
             };
 
-            let delta_site = self.epsilon0 * delta_n_f
-                - self.alpha * ((2.0 * n_mol as f32 * delta_n_f) + 1.0) / v_sites;
-
-            let delta_h = delta_e + delta_site - chem_potential * delta_n_f;
-
-            if delta_h <= 0.0 || random::<f32>() < (-delta_h / temp).exp() {
-                self.grid[x][y] = match current_site {
-                    Site::Molecule => Site::Empty,
-                    Site::Empty => Site::Molecule,
-                };
-                n_mol += delta_n_i;
+            let delta_e = self.hop_delta_e((fx, fy), (tx, ty));
+            if delta_e <= 0.0 || self.rng.random::<f32>() < (-delta_e / temp).exp() {
+                self.grid[fx][fy] = Site::Empty;
+                self.grid[tx][ty] = Site::Molecule;
+                accepted += 1;
             }
         }
-        self.num_molecules = n_mol;
+        accepted
     }
 }
 
+/// Energy without the `-µN` term of `molecules` molecules with `bonds`
+/// occupied bonds on `sites` sites, consistent with the energy differences
+/// used in `Lattice::step`.
+pub fn model_energy(
+    j: f32,
+    epsilon0: f32,
+    alpha: f32,
+    sites: usize,
+    bonds: usize,
+    molecules: usize,
+) -> f64 {
+    let n = molecules as f64;
+    -(j as f64) * bonds as f64 + epsilon0 as f64 * n - alpha as f64 * n * n / sites as f64
+}
+
+/// Per-sweep `(step, T, µ, ρ)` records. With a limit only the most recent
+/// records are kept, so long interactive sessions use bounded memory.
 pub struct SimulationLogger {
-    records: Vec<(u64, f32, f32, f32)>,
+    /// `(step, T, µ, ρ, J, ε0, α)` after every sweep.
+    records: VecDeque<(u64, f32, f32, f32, f32, f32, f32)>,
+    limit: usize,
 }
 
 impl SimulationLogger {
+    #[allow(dead_code)] // ps_cli only
     pub fn new() -> Self {
+        Self::with_limit(usize::MAX)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
         Self {
-            records: Vec::new(),
+            records: VecDeque::new(),
+            limit: limit.max(1),
         }
     }
 
-    pub fn record(&mut self, step: u64, temperature: f32, chem_potential: f32, density: f32) {
-        self.records
-            .push((step, temperature, chem_potential, density));
+    pub fn record(&mut self, step: u64, temperature: f32, chem_potential: f32, lattice: &Lattice) {
+        if self.records.len() == self.limit {
+            self.records.pop_front();
+        }
+        let density = lattice.molecule_count() as f32 / (lattice.width * lattice.height) as f32;
+        self.records.push_back((
+            step,
+            temperature,
+            chem_potential,
+            density,
+            lattice.j,
+            lattice.epsilon0,
+            lattice.alpha,
+        ));
     }
 
+    #[allow(dead_code)] // ps_cli only
     pub fn save_csv(&self, path: impl Into<PathBuf>) -> std::io::Result<()> {
         let path: PathBuf = path.into();
-        if let Some(dir) = path.parent() {
-            if !dir.as_os_str().is_empty() {
-                std::fs::create_dir_all(dir)?;
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.write_csv(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn write_csv(&self, mut out: impl Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "step,temperature,chem_potential,density,interaction,epsilon0,alpha"
+        )?;
+        for (step, t, c, d, j, e, a) in &self.records {
+            writeln!(out, "{step},{t},{c},{d},{j},{e},{a}")?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lattice() -> Lattice {
+        Lattice::new_seeded(10, 8, 1.0, 0.4, 0.7, 0.45, 5)
+    }
+
+    fn neighbors(lattice: &Lattice, x: usize, y: usize) -> [(usize, usize); 4] {
+        let (w, h) = (lattice.width, lattice.height);
+        [
+            (x, (y + h - 1) % h),
+            (x, (y + 1) % h),
+            ((x + w - 1) % w, y),
+            ((x + 1) % w, y),
+        ]
+    }
+
+    /// Occupied nearest-neighbour bonds.
+    fn bonds(lattice: &Lattice) -> i32 {
+        let mut twice = 0;
+        for x in 0..lattice.width {
+            for y in 0..lattice.height {
+                if lattice.grid[x][y] == Site::Molecule {
+                    twice += lattice.neighbor_molecules(x, y);
+                }
             }
         }
-        let mut file = File::create(path)?;
-        writeln!(file, "step,temperature,chem_potential,density")?;
-        for (step, t, c, d) in &self.records {
-            writeln!(file, "{step},{t},{c},{d}")?;
+        twice / 2
+    }
+
+    #[test]
+    fn kawasaki_conserves_molecule_count() {
+        let mut lattice = lattice();
+        let n = lattice.molecule_count();
+        let mut hops = 0;
+        for _ in 0..500 {
+            hops += lattice.step_kawasaki(0.8);
+        }
+        assert!(hops > 0);
+        let counted = lattice
+            .grid
+            .iter()
+            .flatten()
+            .filter(|&&s| s == Site::Molecule);
+        assert_eq!((lattice.molecule_count(), counted.count()), (n, n));
+    }
+
+    #[test]
+    fn hop_delta_e_matches_energy_change() {
+        let mut lattice = lattice();
+        for x in 0..lattice.width {
+            for y in 0..lattice.height {
+                if lattice.grid[x][y] != Site::Molecule {
+                    continue;
+                }
+                for (tx, ty) in neighbors(&lattice, x, y) {
+                    if lattice.grid[tx][ty] != Site::Empty {
+                        continue;
+                    }
+                    let before = bonds(&lattice);
+                    let delta_e = lattice.hop_delta_e((x, y), (tx, ty));
+                    lattice.grid[x][y] = Site::Empty;
+                    lattice.grid[tx][ty] = Site::Molecule;
+                    let after = bonds(&lattice);
+                    lattice.grid[tx][ty] = Site::Empty;
+                    lattice.grid[x][y] = Site::Molecule;
+                    // At fixed N only the bond term −J·B changes.
+                    assert_eq!(delta_e, -lattice.j * (after - before) as f32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn delta_h_matches_model_energy_for_single_flips() {
+        let mut lattice = lattice();
+        let mu = -0.8;
+        let grand = |lattice: &Lattice| lattice.energy() - mu * lattice.molecule_count() as f64;
+        for x in 0..lattice.width {
+            for y in 0..lattice.height {
+                let before = grand(&lattice);
+                let delta_h = lattice.delta_h(x, y, mu as f32) as f64;
+                lattice.flip(x, y);
+                let after = grand(&lattice);
+                lattice.flip(x, y);
+                assert!((after - before - delta_h).abs() < 1e-4, "({x}, {y})");
+            }
         }
-        Ok(())
     }
 }
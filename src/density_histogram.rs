@@ -0,0 +1,111 @@
+use crate::core::Lattice;
+use macroquad::prelude::*;
+
+/// Histogram of the molecule count sampled by the running simulation, for
+/// comparison with the mean-field `f_tc(ρ)`.
+pub struct DensityHistogram {
+    counts: Vec<u64>,
+    samples: u64,
+    params: (f32, f32),
+}
+
+impl DensityHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: Vec::new(),
+            samples: 0,
+            params: (f32::NAN, f32::NAN),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.counts.fill(0);
+        self.samples = 0;
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Adds the current molecule count; the histogram restarts whenever T, µ
+    /// or the lattice size change.
+    pub fn record(&mut self, lattice: &Lattice, temp: f32, chem_potential: f32) {
+        let sites = lattice.width * lattice.height;
+        if self.counts.len() != sites + 1 {
+            self.counts = vec![0; sites + 1];
+            self.samples = 0;
+        }
+        if self.params != (temp, chem_potential) {
+            self.params = (temp, chem_potential);
+            self.reset();
+        }
+        self.counts[lattice.molecule_count()] += 1;
+        self.samples += 1;
+    }
+
+    /// `(ρ, ln P(ρ) / V)` for every sampled density, with the most probable
+    /// density at zero. Up to a constant this is `−βF(ρ)/V`, the quantity
+    /// `calculate_ftc` approximates.
+    pub fn ln_p_per_site(&self) -> Vec<(f32, f32)> {
+        let sites = self.counts.len().saturating_sub(1).max(1) as f64;
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return Vec::new();
+        }
+        let ln_max = (max as f64).ln();
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c > 0)
+            .map(|(n, &c)| {
+                (
+                    (n as f64 / sites) as f32,
+                    (((c as f64).ln() - ln_max) / sites) as f32,
+                )
+            })
+            .collect()
+    }
+
+    /// Draws the histogram as bars along the bottom of `rect`, in `bins`
+    /// density bins, and `ln P(ρ)/V` as points on the `f_range` axis of the
+    /// free-energy plot with its maximum at `f_top`.
+    pub fn draw(&self, rect: Rect, bins: usize, f_range: (f32, f32), f_top: f32) {
+        if self.samples == 0 {
+            return;
+        }
+        let sites = self.counts.len().saturating_sub(1).max(1);
+        let bins = bins.max(1);
+        let mut binned = vec![0u64; bins];
+        for (n, &c) in self.counts.iter().enumerate() {
+            binned[(n * bins / (sites + 1)).min(bins - 1)] += c;
+        }
+        let peak = binned.iter().copied().max().unwrap_or(1).max(1) as f32;
+        let bar_w = rect.w / bins as f32;
+        let bar_h = rect.h * 0.3;
+        for (i, &c) in binned.iter().enumerate() {
+            if c == 0 {
+                continue;
+            }
+            let h = bar_h * c as f32 / peak;
+            draw_rectangle(
+                rect.x + i as f32 * bar_w,
+                rect.y + rect.h - h,
+                bar_w.max(1.0),
+                h,
+                color_u8!(102, 191, 255, 90),
+            );
+        }
+
+        let (min_f, max_f) = f_range;
+        let span = (max_f - min_f).max(0.001);
+        for (d, ln_p) in self.ln_p_per_site() {
+            let f = f_top + ln_p;
+            if f < min_f {
+                continue;
+            }
+            let x = rect.x + d * rect.w;
+            let y = rect.y + rect.h - ((f - min_f) / span) * rect.h;
+            draw_circle(x, y, 2.0, SKYBLUE);
+        }
+    }
+}
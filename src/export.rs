@@ -0,0 +1,129 @@
+use crate::control_panel::Settings;
+use crate::core::{Lattice, SimulationLogger};
+use crate::lattice_image::{self, GifRecorder};
+use macroquad::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Sweeps kept by the GUI's time-series log; older sweeps are discarded.
+pub const LOG_LIMIT: usize = 1_000_000;
+
+/// Seconds a notice stays on screen.
+const NOTICE_SECONDS: f64 = 5.0;
+
+/// Seed of the running lattice and the sweep at which it was built; runs
+/// with the same seed and parameters are identical until the lattice is
+/// edited by hand.
+#[derive(Clone, Copy)]
+pub struct Origin {
+    pub seed: u64,
+    pub step: u64,
+}
+
+/// Pixels per site so that exported images are at least about 800 pixels
+/// wide.
+fn image_scale(lattice: &Lattice) -> usize {
+    (800 / lattice.width.max(lattice.height)).max(1)
+}
+
+/// `dir/name`, creating `dir` if needed.
+fn output_path(dir: &Path, name: String) -> io::Result<PathBuf> {
+    if !dir.as_os_str().is_empty() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(dir.join(name))
+}
+
+/// Writes the time-series log to `dir/simulation_{step}_steps.csv`, preceded
+/// by `#` comment lines with the seed and the model parameters at the time
+/// of export.
+pub fn save_csv(
+    dir: &Path,
+    logger: &SimulationLogger,
+    settings: &Settings,
+    origin: Origin,
+    step: u64,
+) -> io::Result<PathBuf> {
+    let path = output_path(dir, format!("simulation_{step}_steps.csv"))?;
+    let mut out = BufWriter::new(File::create(&path)?);
+    writeln!(out, "# seed = {}", origin.seed)?;
+    writeln!(out, "# seed_step = {}", origin.step)?;
+    writeln!(out, "# width = {}", settings.width)?;
+    writeln!(out, "# height = {}", settings.height)?;
+    writeln!(out, "# init_density = {}", settings.init_density)?;
+    writeln!(out, "# interaction = {}", settings.j)?;
+    writeln!(out, "# epsilon0 = {}", settings.epsilon0)?;
+    writeln!(out, "# alpha = {}", settings.alpha)?;
+    writeln!(out, "# temperature = {}", settings.temperature)?;
+    writeln!(out, "# chem_potential = {}", settings.chem_potential)?;
+    writeln!(out, "# sweeps_per_frame = {}", settings.sweeps_per_frame)?;
+    writeln!(out, "# log_limit = {LOG_LIMIT}")?;
+    logger.write_csv(out)?;
+    Ok(path)
+}
+
+pub fn save_png(dir: &Path, lattice: &Lattice, step: u64) -> io::Result<PathBuf> {
+    let path = output_path(dir, format!("lattice_{step}_steps.png"))?;
+    let out = BufWriter::new(File::create(&path)?);
+    lattice_image::write_png(lattice, image_scale(lattice), out)?;
+    Ok(path)
+}
+
+pub fn start_gif(
+    dir: &Path,
+    lattice: &Lattice,
+    step: u64,
+) -> io::Result<(GifRecorder<BufWriter<File>>, PathBuf)> {
+    let path = output_path(dir, format!("lattice_{step}_steps.gif"))?;
+    let out = BufWriter::new(File::create(&path)?);
+    let recorder = GifRecorder::new(out, lattice, image_scale(lattice), 4)?;
+    Ok((recorder, path))
+}
+
+/// Outcome of the last export, shown over the lattice view for a few
+/// seconds.
+pub struct Notice {
+    message: Option<(String, Color, f64)>,
+}
+
+impl Notice {
+    pub fn new() -> Self {
+        Self { message: None }
+    }
+
+    pub fn success(&mut self, text: String) {
+        self.message = Some((text, LIME, get_time()));
+    }
+
+    pub fn failure(&mut self, text: String) {
+        self.message = Some((text, RED, get_time()));
+    }
+
+    /// Reports `result` as "`what` `path`" or "`what` failed: error".
+    pub fn report(&mut self, what: &str, result: &io::Result<PathBuf>) {
+        match result {
+            Ok(path) => self.success(format!("{what} {}", path.display())),
+            Err(e) => self.failure(format!("{what} failed: {e}")),
+        }
+    }
+
+    pub fn draw(&self, rect: Rect) {
+        let Some((text, color, since)) = &self.message else {
+            return;
+        };
+        if get_time() - since > NOTICE_SECONDS {
+            return;
+        }
+        let dims = measure_text(text, None, 18, 1.0);
+        let (x, y) = (rect.x + 8.0, rect.y + rect.h - 40.0);
+        draw_rectangle(
+            x - 4.0,
+            y - 16.0,
+            dims.width + 8.0,
+            22.0,
+            Color::new(0.0, 0.0, 0.0, 0.75),
+        );
+        draw_text(text, x, y, 18.0, *color);
+    }
+}
@@ -0,0 +1,261 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn scale(self, s: f64) -> Self {
+        Self::new(self.re * s, self.im * s)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self::new(self.re + o.re, self.im + o.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Self::new(self.re - o.re, self.im - o.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        Self::new(
+            self.re * o.re - self.im * o.im,
+            self.re * o.im + self.im * o.re,
+        )
+    }
+}
+
+/// Iterative radix-2 transform; `data.len()` must be a power of two.
+fn radix2(data: &mut [Complex], inverse: bool) {
+    let n = data.len();
+    if n <= 1 {
+        return;
+    }
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let w_len = Complex::from_polar(1.0, sign * 2.0 * PI / len as f64);
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let u = data[start + k];
+                let v = data[start + k + len / 2] * w;
+                data[start + k] = u + v;
+                data[start + k + len / 2] = u - v;
+                w = w * w_len;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+enum Plan {
+    Radix2,
+    /// Bluestein's chirp-z algorithm for lengths that are not a power of two:
+    /// the length-`n` transform becomes a convolution of padded length `m`.
+    Bluestein {
+        m: usize,
+        chirp: Vec<Complex>,
+        kernel_fft: Vec<Complex>,
+    },
+}
+
+/// Unnormalised discrete Fourier transform of a fixed length.
+pub struct Fft {
+    n: usize,
+    plan: Plan,
+}
+
+impl Fft {
+    pub fn new(n: usize) -> Self {
+        if n.is_power_of_two() || n <= 1 {
+            return Self {
+                n,
+                plan: Plan::Radix2,
+            };
+        }
+        let m = (2 * n - 1).next_power_of_two();
+        let chirp: Vec<Complex> = (0..n)
+            .map(|k| {
+                let k2 = (k * k) % (2 * n);
+                Complex::from_polar(1.0, -PI * k2 as f64 / n as f64)
+            })
+            .collect();
+        let mut kernel_fft = vec![Complex::default(); m];
+        kernel_fft[0] = chirp[0].conj();
+        for k in 1..n {
+            kernel_fft[k] = chirp[k].conj();
+            kernel_fft[m - k] = chirp[k].conj();
+        }
+        radix2(&mut kernel_fft, false);
+        Self {
+            n,
+            plan: Plan::Bluestein {
+                m,
+                chirp,
+                kernel_fft,
+            },
+        }
+    }
+
+    pub fn process(&self, data: &mut [Complex], inverse: bool) {
+        debug_assert_eq!(data.len(), self.n);
+        match &self.plan {
+            Plan::Radix2 => radix2(data, inverse),
+            Plan::Bluestein {
+                m,
+                chirp,
+                kernel_fft,
+            } => {
+                // The inverse transform is the conjugate of the forward transform
+                // of the conjugated input.
+                let mut a = vec![Complex::default(); *m];
+                for k in 0..self.n {
+                    let x = if inverse { data[k].conj() } else { data[k] };
+                    a[k] = x * chirp[k];
+                }
+                radix2(&mut a, false);
+                for (ak, bk) in a.iter_mut().zip(kernel_fft) {
+                    *ak = *ak * *bk;
+                }
+                radix2(&mut a, true);
+                let norm = 1.0 / *m as f64;
+                for k in 0..self.n {
+                    let y = (a[k] * chirp[k]).scale(norm);
+                    data[k] = if inverse { y.conj() } else { y };
+                }
+            }
+        }
+    }
+}
+
+/// Unnormalised 2D transform of a `width × height` field stored as
+/// `data[x * height + y]`, matching the `grid[x][y]` layout of `Lattice`.
+pub struct Fft2 {
+    width: usize,
+    height: usize,
+    along_x: Fft,
+    along_y: Fft,
+}
+
+impl Fft2 {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            along_x: Fft::new(width),
+            along_y: Fft::new(height),
+        }
+    }
+
+    pub fn process(&self, data: &mut [Complex], inverse: bool) {
+        debug_assert_eq!(data.len(), self.width * self.height);
+        for column in data.chunks_mut(self.height) {
+            self.along_y.process(column, inverse);
+        }
+        let mut row = vec![Complex::default(); self.width];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                row[x] = data[x * self.height + y];
+            }
+            self.along_x.process(&mut row, inverse);
+            for x in 0..self.width {
+                data[x * self.height + y] = row[x];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(n: usize) -> Vec<Complex> {
+        (0..n)
+            .map(|k| Complex::new((0.7 * k as f64).sin() + 0.3, (1.3 * k as f64).cos()))
+            .collect()
+    }
+
+    fn naive_dft(data: &[Complex]) -> Vec<Complex> {
+        let n = data.len();
+        (0..n)
+            .map(|k| {
+                data.iter()
+                    .enumerate()
+                    .fold(Complex::default(), |sum, (j, &x)| {
+                        let theta = -2.0 * PI * ((j * k) % n) as f64 / n as f64;
+                        sum + x * Complex::from_polar(1.0, theta)
+                    })
+            })
+            .collect()
+    }
+
+    fn assert_close(a: &[Complex], b: &[Complex]) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((*x - *y).norm_sqr().sqrt() < 1e-9, "{x:?} != {y:?}");
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_forward() {
+        for n in [1, 2, 8, 64, 3, 12, 45] {
+            let input = signal(n);
+            let fft = Fft::new(n);
+            let mut data = input.clone();
+            fft.process(&mut data, false);
+            fft.process(&mut data, true);
+            let data: Vec<Complex> = data.iter().map(|x| x.scale(1.0 / n as f64)).collect();
+            assert_close(&data, &input);
+        }
+    }
+
+    #[test]
+    fn matches_naive_dft() {
+        for n in [16, 7, 12, 30] {
+            let input = signal(n);
+            let mut data = input.clone();
+            Fft::new(n).process(&mut data, false);
+            assert_close(&data, &naive_dft(&input));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::clusters::Clusters;
+use crate::core::{Lattice, Site};
+
+impl Lattice {
+    /// Number of occupied–empty nearest-neighbour bonds on the periodic lattice.
+    pub fn interface_length(&self) -> usize {
+        let mut count = 0;
+        for x in 0..self.width {
+            let right = (x + 1) % self.width;
+            for y in 0..self.height {
+                let down = (y + 1) % self.height;
+                let here = self.grid[x][y] == Site::Molecule;
+                count += (here != (self.grid[right][y] == Site::Molecule)) as usize;
+                count += (here != (self.grid[x][down] == Site::Molecule)) as usize;
+            }
+        }
+        count
+    }
+
+    /// Fraction of the `2N` nearest-neighbour bonds that cross an interface.
+    pub fn interface_density(&self) -> f64 {
+        self.interface_length() as f64 / (2 * self.width * self.height) as f64
+    }
+
+    /// Box-counting dimension of the boundary of cluster `id`, i.e. of its
+    /// sites with at least one empty neighbour.
+    ///
+    /// Boxes of side `1, 2, 4, …` up to a quarter of the shorter lattice edge
+    /// are laid over the lattice and the slope of `ln N(s)` against `ln s` is
+    /// fitted. Returns `None` when fewer than three box sizes fit.
+    pub fn boundary_fractal_dimension(&self, clusters: &Clusters, id: usize) -> Option<f64> {
+        let (w, h) = (self.width, self.height);
+        let mut boundary = Vec::new();
+        for x in 0..w {
+            for y in 0..h {
+                if clusters.labels[x][y] != Some(id) {
+                    continue;
+                }
+                let neighbors = [
+                    (x, (y + h - 1) % h),
+                    (x, (y + 1) % h),
+                    ((x + w - 1) % w, y),
+                    ((x + 1) % w, y),
+                ];
+                if neighbors
+                    .iter()
+                    .any(|&(nx, ny)| self.grid[nx][ny] == Site::Empty)
+                {
+                    boundary.push((x, y));
+                }
+            }
+        }
+        if boundary.is_empty() {
+            return None;
+        }
+
+        let mut points = Vec::new();
+        let mut size = 1;
+        while size <= w.min(h) / 4 {
+            let (bw, bh) = (w.div_ceil(size), h.div_ceil(size));
+            let mut occupied = vec![false; bw * bh];
+            for &(x, y) in &boundary {
+                occupied[(x / size) * bh + y / size] = true;
+            }
+            let boxes = occupied.iter().filter(|&&b| b).count();
+            points.push(((size as f64).ln(), (boxes as f64).ln()));
+            size *= 2;
+        }
+        if points.len() < 3 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_s = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_b = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let cov: f64 = points.iter().map(|p| (p.0 - mean_s) * (p.1 - mean_b)).sum();
+        let var: f64 = points.iter().map(|p| (p.0 - mean_s).powi(2)).sum();
+        Some(-cov / var)
+    }
+}
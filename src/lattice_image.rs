@@ -0,0 +1,248 @@
+use crate::core::{Lattice, Site};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Colours of occupied and empty sites, also used by the GUI.
+pub const MOLECULE_RGB: [u8; 3] = [23, 43, 54];
+pub const EMPTY_RGB: [u8; 3] = [217, 232, 227];
+
+/// Palette index of every pixel, row by row, with each site drawn as a
+/// `scale` × `scale` block: 0 for empty, 1 for occupied sites.
+fn pixels(lattice: &Lattice, scale: usize) -> Vec<u8> {
+    let width = lattice.width * scale;
+    let mut pixels = Vec::with_capacity(width * lattice.height * scale);
+    for y in 0..lattice.height {
+        let row: Vec<u8> = (0..width)
+            .map(|px| u8::from(lattice.grid[px / scale][y] == Site::Molecule))
+            .collect();
+        for _ in 0..scale {
+            pixels.extend_from_slice(&row);
+        }
+    }
+    pixels
+}
+
+fn image_size(lattice: &Lattice, scale: usize, max: usize) -> io::Result<(usize, usize)> {
+    let (w, h) = (lattice.width * scale, lattice.height * scale);
+    if scale == 0 || w > max || h > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("image of {w}×{h} pixels is empty or exceeds {max} pixels per side"),
+        ));
+    }
+    Ok((w, h))
+}
+
+/// Indexed-colour PNG of the configuration, `scale` pixels per site.
+pub fn write_png(lattice: &Lattice, scale: usize, out: impl Write) -> io::Result<()> {
+    let (w, h) = image_size(lattice, scale, u32::MAX as usize)?;
+    let mut encoder = png::Encoder::new(out, w as u32, h as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette([EMPTY_RGB, MOLECULE_RGB].concat());
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&pixels(lattice, scale))
+        .map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// GIF89a animation of successive configurations, looping forever. Frames
+/// are appended one at a time; `finish` writes the trailer.
+pub struct GifRecorder<W: Write> {
+    out: W,
+    size: (usize, usize),
+    scale: usize,
+    /// Display time of every frame in hundredths of a second.
+    delay: u16,
+    frames: usize,
+}
+
+/// Codes start at 3 bits for the 4-entry colour table.
+const MIN_CODE_SIZE: u8 = 2;
+
+impl<W: Write> GifRecorder<W> {
+    /// Writes the header for frames of `lattice`'s size; every later frame
+    /// must have the same size.
+    pub fn new(mut out: W, lattice: &Lattice, scale: usize, delay: u16) -> io::Result<Self> {
+        let (w, h) = image_size(lattice, scale, u16::MAX as usize)?;
+        out.write_all(b"GIF89a")?;
+        out.write_all(&(w as u16).to_le_bytes())?;
+        out.write_all(&(h as u16).to_le_bytes())?;
+        // Global colour table of 2^(1 + 1) entries, 8 bits per primary.
+        out.write_all(&[0xF1, 0, 0])?;
+        out.write_all(&[EMPTY_RGB, MOLECULE_RGB, [0; 3], [0; 3]].concat())?;
+        // NETSCAPE2.0 application extension: loop forever.
+        out.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(Self {
+            out,
+            size: (lattice.width, lattice.height),
+            scale,
+            delay,
+            frames: 0,
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn add_frame(&mut self, lattice: &Lattice) -> io::Result<()> {
+        if (lattice.width, lattice.height) != self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "lattice size changed during GIF recording",
+            ));
+        }
+        let (w, h) = (self.size.0 * self.scale, self.size.1 * self.scale);
+        // Graphic control extension with the frame delay.
+        self.out.write_all(&[0x21, 0xF9, 0x04, 0x00])?;
+        self.out.write_all(&self.delay.to_le_bytes())?;
+        self.out.write_all(&[0x00, 0x00])?;
+        // Image descriptor covering the whole canvas, no local colour table.
+        self.out.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.out.write_all(&(w as u16).to_le_bytes())?;
+        self.out.write_all(&(h as u16).to_le_bytes())?;
+        self.out.write_all(&[0x00, MIN_CODE_SIZE])?;
+        for block in lzw_encode(&pixels(lattice, self.scale)).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0x00])?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.write_all(&[0x3B])?;
+        self.out.flush()
+    }
+}
+
+/// Variable-width LZW as GIF uses it: codes packed least significant bit
+/// first, widening once the next free code no longer fits, with a clear code
+/// when the 12-bit table is full.
+fn lzw_encode(pixels: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << MIN_CODE_SIZE;
+    let end = clear + 1;
+    let mut out = Vec::new();
+    let (mut bits, mut n_bits) = (0u32, 0u32);
+    let mut emit = |code: u16, width: u32| {
+        bits |= (code as u32) << n_bits;
+        n_bits += width;
+        while n_bits >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            n_bits -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut width = MIN_CODE_SIZE as u32 + 1;
+    let mut next = end + 1;
+    emit(clear, width);
+    if let Some((&first, rest)) = pixels.split_first() {
+        let mut prefix = first as u16;
+        for &pixel in rest {
+            if let Some(&code) = table.get(&(prefix, pixel)) {
+                prefix = code;
+                continue;
+            }
+            emit(prefix, width);
+            if next >= 1 << width && width < 12 {
+                width += 1;
+            }
+            if next >= 4095 {
+                emit(clear, width);
+                table.clear();
+                width = MIN_CODE_SIZE as u32 + 1;
+                next = end + 1;
+            } else {
+                table.insert((prefix, pixel), next);
+                next += 1;
+            }
+            prefix = pixel as u16;
+        }
+        emit(prefix, width);
+        if next >= 1 << width && width < 12 {
+            width += 1;
+        }
+    }
+    emit(end, width);
+    if n_bits > 0 {
+        out.push(bits as u8);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Reference GIF decoder: the pixels and the number of clear codes.
+    fn lzw_decode(data: &[u8]) -> (Vec<u8>, usize) {
+        let clear = 1usize << MIN_CODE_SIZE;
+        let end = clear + 1;
+        let mut table: Vec<Vec<u8>> = (0..clear).map(|c| vec![c as u8]).collect();
+        table.extend([Vec::new(), Vec::new()]);
+        let (mut pixels, mut clears) = (Vec::new(), 0);
+        let mut width = MIN_CODE_SIZE as usize + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut position = 0;
+        loop {
+            let code = (0..width)
+                .map(|k| {
+                    let bit = position + k;
+                    ((data[bit / 8] >> (bit % 8)) as usize & 1) << k
+                })
+                .sum::<usize>();
+            position += width;
+            if code == clear {
+                table.truncate(end + 1);
+                width = MIN_CODE_SIZE as usize + 1;
+                previous = None;
+                clears += 1;
+                continue;
+            }
+            if code == end {
+                return (pixels, clears);
+            }
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) if code == table.len() => {
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                }
+                _ => panic!("code {code} is not in the table"),
+            };
+            pixels.extend(&entry);
+            if let Some(mut previous) = previous.take() {
+                previous.push(entry[0]);
+                table.push(previous);
+            }
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_decodes_to_the_input() {
+        for pixels in [vec![], vec![1], vec![0, 0, 0, 1, 1, 0, 1, 0, 0, 0, 0, 0]] {
+            assert_eq!(lzw_decode(&lzw_encode(&pixels)), (pixels, 1));
+        }
+    }
+
+    #[test]
+    fn lzw_decodes_across_a_full_table() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let pixels: Vec<u8> = (0..60_000).map(|_| rng.random_range(0..4)).collect();
+        let (decoded, clears) = lzw_decode(&lzw_encode(&pixels));
+        assert!(clears > 2, "{clears} clear codes");
+        assert!(decoded == pixels);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::core::Lattice;
+use macroquad::prelude::*;
+
+const MAX_ZOOM: f32 = 64.0;
+const ZOOM_STEP: f32 = 1.25;
+
+/// The lattice drawn through a texture refreshed from a pixel buffer every
+/// frame, one texel per site, with mouse-wheel zoom about the cursor and
+/// middle- or Ctrl-drag panning.
+pub struct LatticeView {
+    image: Image,
+    texture: Texture2D,
+    zoom: f32,
+    /// Lattice coordinates of the top-left corner of the visible region.
+    origin: Vec2,
+    /// Cursor on the previous frame while panning.
+    drag: Option<Vec2>,
+}
+
+impl LatticeView {
+    pub fn new() -> Self {
+        let image = Image::gen_image_color(1, 1, BLANK);
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest);
+        Self {
+            image,
+            texture,
+            zoom: 1.0,
+            origin: Vec2::ZERO,
+            drag: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.origin = Vec2::ZERO;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Visible part of the lattice, in lattice coordinates.
+    fn visible(&self, lattice: &Lattice) -> Rect {
+        Rect::new(
+            self.origin.x,
+            self.origin.y,
+            lattice.width as f32 / self.zoom,
+            lattice.height as f32 / self.zoom,
+        )
+    }
+
+    fn to_lattice(&self, lattice: &Lattice, rect: Rect, p: Vec2) -> Vec2 {
+        let v = self.visible(lattice);
+        vec2(
+            v.x + (p.x - rect.x) / rect.w * v.w,
+            v.y + (p.y - rect.y) / rect.h * v.h,
+        )
+    }
+
+    pub fn to_screen(&self, lattice: &Lattice, rect: Rect, q: Vec2) -> Vec2 {
+        let v = self.visible(lattice);
+        vec2(
+            rect.x + (q.x - v.x) / v.w * rect.w,
+            rect.y + (q.y - v.y) / v.h * rect.h,
+        )
+    }
+
+    /// On-screen size of one site.
+    pub fn cell_size(&self, lattice: &Lattice, rect: Rect) -> Vec2 {
+        vec2(
+            rect.w * self.zoom / lattice.width as f32,
+            rect.h * self.zoom / lattice.height as f32,
+        )
+    }
+
+    /// Site under the screen point `p`, or `None` outside `rect`.
+    pub fn site_at(&self, lattice: &Lattice, rect: Rect, p: Vec2) -> Option<(i64, i64)> {
+        if !rect.contains(p) {
+            return None;
+        }
+        let q = self.to_lattice(lattice, rect, p);
+        Some((q.x.floor() as i64, q.y.floor() as i64))
+    }
+
+    /// Mouse-wheel zoom keeping the site under the cursor in place, and
+    /// panning by middle- or Ctrl-left drag that started inside `rect`.
+    pub fn handle_input(&mut self, lattice: &Lattice, rect: Rect) {
+        let mouse: Vec2 = mouse_position().into();
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0.0 && rect.contains(mouse) {
+            let anchor = self.to_lattice(lattice, rect, mouse);
+            self.zoom = (self.zoom * ZOOM_STEP.powf(wheel.signum())).clamp(1.0, MAX_ZOOM);
+            let v = self.visible(lattice);
+            self.origin = anchor - (mouse - rect.point()) / rect.size() * v.size();
+        }
+
+        let pan_button = is_mouse_button_down(MouseButton::Middle)
+            || (is_key_down(KeyCode::LeftControl) && is_mouse_button_down(MouseButton::Left));
+        self.drag = match self.drag {
+            Some(last) if pan_button => {
+                let v = self.visible(lattice);
+                self.origin -= (mouse - last) / rect.size() * v.size();
+                Some(mouse)
+            }
+            None if pan_button && rect.contains(mouse) => Some(mouse),
+            _ => None,
+        };
+
+        let v = self.visible(lattice);
+        let max = vec2(lattice.width as f32 - v.w, lattice.height as f32 - v.h);
+        self.origin = self.origin.clamp(Vec2::ZERO, max);
+    }
+
+    /// Fills the pixel buffer with `color(x, y)` for every site, uploads it
+    /// and draws the visible region into `rect`.
+    pub fn draw(&mut self, lattice: &Lattice, rect: Rect, color: impl Fn(usize, usize) -> Color) {
+        let (w, h) = (lattice.width, lattice.height);
+        if (self.image.width(), self.image.height()) != (w, h) {
+            self.image = Image::gen_image_color(w as u16, h as u16, BLANK);
+            self.texture = Texture2D::from_image(&self.image);
+            self.texture.set_filter(FilterMode::Nearest);
+        }
+        for (i, pixel) in self.image.bytes.chunks_exact_mut(4).enumerate() {
+            let rgba: [u8; 4] = color(i % w, i / w).into();
+            pixel.copy_from_slice(&rgba);
+        }
+        self.texture.update(&self.image);
+        draw_texture_ex(
+            &self.texture,
+            rect.x,
+            rect.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(rect.size()),
+                source: Some(self.visible(lattice)),
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -1,280 +1,110 @@
 #![allow(clippy::needless_range_loop)]
 
 use macroquad::prelude::*;
-mod density_plot;
-use ::rand::{Rng, random, rng};
-use density_plot::DensityPopup;
 use std::fs::File;
-use std::io::Write;
+use std::io::BufWriter;
 use std::path::PathBuf;
+mod brush;
+mod cluster_view;
+mod clusters;
+mod conductance;
+mod control_panel;
+mod core;
+mod density_histogram;
+mod export;
+mod fft;
+mod interface;
+mod lattice_image;
+mod lattice_view;
+mod mc_phase_map;
+mod mean_field;
+mod onsager;
+mod paint;
+mod phase_diagram;
+mod plot;
+mod protocol;
+mod quasi_chemical;
+mod session;
+mod structure_factor;
+mod structure_factor_plot;
+mod time_series;
+use brush::Brush;
+use cluster_view::{ClusterStats, ClusterView};
+use clusters::Boundary;
+use conductance::Conductance;
+use control_panel::{ControlPanel, Pacer, Settings};
+use core::{Lattice, SimulationLogger, Site};
+use density_histogram::DensityHistogram;
+use export::{Notice, Origin};
+use lattice_image::{EMPTY_RGB, GifRecorder, MOLECULE_RGB};
+use lattice_view::LatticeView;
+use mean_field::{MeanField, Theory, Z};
+use onsager::Onsager;
+use phase_diagram::PhaseDiagram;
+use protocol::Action;
+use quasi_chemical::QuasiChemical;
+use session::{Entry, Recorder, Replay};
+use structure_factor_plot::StructureFactorPanel;
+use time_series::{Observable, TimeSeriesPopup};
 
 fn phase_color_bright() -> Color {
-    color_u8!(217, 232, 227, 255)
+    let [r, g, b] = EMPTY_RGB;
+    color_u8!(r, g, b, 255)
 }
 fn phase_color_dark() -> Color {
-    color_u8!(23, 43, 54, 255)
+    let [r, g, b] = MOLECULE_RGB;
+    color_u8!(r, g, b, 255)
 }
 
 const GRID_WIDTH: usize = 200;
 const GRID_HEIGHT: usize = 200;
 
+/// Sweeps between frames of a GIF recording.
+const GIF_FRAME_SWEEPS: u64 = 10;
+/// Sweeps between conductance measurements in the time-series popup.
+const CONDUCTANCE_SWEEPS: u64 = 10;
+
 const J_MF: f32 = 1.0;
-const Z: f32 = 4.0;
 const J0: f32 = 2.0 * J_MF / Z;
 
-#[derive(Clone, Copy, PartialEq)]
-enum Site {
-    Molecule,
-    Empty,
-}
-
-struct Lattice {
-    grid: Vec<Vec<Site>>,
-    width: usize,
-    height: usize,
-}
-
-impl Lattice {
-    fn new(width: usize, height: usize) -> Self {
-        let mut grid = vec![vec![Site::Empty; height]; width];
-        for x in 0..width {
-            for y in 0..height {
-                if random::<bool>() {
-                    grid[x][y] = Site::Molecule;
-                }
-            }
-        }
-        Lattice {
-            grid,
-            width,
-            height,
-        }
-    }
-
-    fn molecule_count(&self) -> usize {
-        self.grid
-            .iter()
-            .flatten()
-            .filter(|&&s| s == Site::Molecule)
-            .count()
-    }
-
-    fn step(&mut self, temp: f32, chem_potential: f32) {
-        if temp <= 0.0 {
-            return;
-        }
-        let mut rng = rng();
-        for _ in 0..(self.width * self.height) {
-            let x = rng.random_range(0..self.width);
-            let y = rng.random_range(0..self.height);
-            let current_site = self.grid[x][y];
-
-            let mut neighbor_molecules = 0;
-            let up = (y + self.height - 1) % self.height;
-            let down = (y + 1) % self.height;
-            let left = (x + self.width - 1) % self.width;
-            let right = (x + 1) % self.width;
-
-            if self.grid[x][up] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-            if self.grid[x][down] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-            if self.grid[left][y] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-            if self.grid[right][y] == Site::Molecule {
-                neighbor_molecules += 1;
-            }
-
-            let delta_e = match current_site {
-                Site::Empty => -J0 * neighbor_molecules as f32,
-                Site::Molecule => J0 * neighbor_molecules as f32,
-            };
-            let delta_n = match current_site {
-                Site::Empty => 1.0,
-                Site::Molecule => -1.0,
-            };
-            let delta_h = delta_e - chem_potential * delta_n;
-
-            if delta_h <= 0.0 || random::<f32>() < (-delta_h / temp).exp() {
-                self.grid[x][y] = match current_site {
-                    Site::Molecule => Site::Empty,
-                    Site::Empty => Site::Molecule,
-                };
-            }
-        }
-    }
-
-    fn draw(&self, rect: Rect) {
-        let cell_w = rect.w / self.width as f32;
-        let cell_h = rect.h / self.height as f32;
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let color = match self.grid[x][y] {
-                    Site::Molecule => phase_color_dark(),
-                    Site::Empty => phase_color_bright(),
-                };
-                draw_rectangle(
-                    rect.x + x as f32 * cell_w,
-                    rect.y + y as f32 * cell_h,
-                    cell_w,
-                    cell_h,
-                    color,
-                );
-            }
-        }
-    }
-}
-
 #[derive(PartialEq)]
 enum Mode {
     UI,
     PhaseDiagram,
     FreeEnergyPlot,
+    Clusters,
+    StructureFactor,
 }
 
-struct PhaseDiagram {
-    densities: Vec<Vec<f32>>,
-    temp_range: (f32, f32),
-    chem_potential_range: (f32, f32),
-    resolution: (usize, usize),
-}
-
-impl PhaseDiagram {
-    fn new(
-        resolution_t: usize,
-        resolution_c: usize,
-        temp_range: (f32, f32),
-        chem_potential_range: (f32, f32),
-    ) -> Self {
-        let mut densities = vec![vec![0.0; resolution_c]; resolution_t];
-        for i in 0..resolution_t {
-            let temp =
-                temp_range.0 + (i as f32 / resolution_t as f32) * (temp_range.1 - temp_range.0);
-            for j in 0..resolution_c {
-                let chem_potential = chem_potential_range.0
-                    + (j as f32 / resolution_c as f32)
-                        * (chem_potential_range.1 - chem_potential_range.0);
-
-                let mut max_f = -f32::INFINITY;
-                let mut best_d = 0.0;
-                for k in 1..1000 {
-                    let d = k as f32 / 1000.0;
-                    let f = calculate_ftc(d, temp, chem_potential);
-                    if f > max_f {
-                        max_f = f;
-                        best_d = d;
-                    }
-                }
-                densities[i][j] = best_d;
-            }
-        }
-        PhaseDiagram {
-            densities,
-            temp_range,
-            chem_potential_range,
-            resolution: (resolution_t, resolution_c),
-        }
-    }
-
-    fn draw(&self, rect: Rect, current_temp: f32, current_chem_potential: f32) {
-        let (res_t, res_c) = self.resolution;
-        let cell_w = rect.w / res_t as f32;
-        let cell_h = rect.h / res_c as f32;
-
-        for i in 0..res_t {
-            for j in 0..res_c {
-                let density = self.densities[i][j].clamp(0.0, 1.0);
-                let bright = phase_color_bright();
-                let dark = phase_color_dark();
-                let r = bright.r + density * (dark.r - bright.r);
-                let g = bright.g + density * (dark.g - bright.g);
-                let b = bright.b + density * (dark.b - bright.b);
-                let color = Color { r, g, b, a: 1.0 };
-                draw_rectangle(
-                    rect.x + i as f32 * cell_w,
-                    rect.y + j as f32 * cell_h,
-                    cell_w,
-                    cell_h,
-                    color,
-                );
-            }
-        }
-
-        let t_frac = (current_temp - self.temp_range.0) / (self.temp_range.1 - self.temp_range.0);
-        let c_frac = (current_chem_potential - self.chem_potential_range.0)
-            / (self.chem_potential_range.1 - self.chem_potential_range.0);
-        if (0.0..=1.0).contains(&t_frac) && (0.0..=1.0).contains(&c_frac) {
-            let marker_x = rect.x + t_frac * rect.w;
-            let marker_y = rect.y + c_frac * rect.h;
-            draw_circle(marker_x, marker_y, 5.0, RED);
-        }
-
-        draw_text(
-            "T",
-            rect.x + rect.w / 2.0 - 5.0,
-            rect.y + rect.h + 20.0,
-            20.0,
-            WHITE,
-        );
-        draw_text("µ", rect.x - 25.0, rect.y + rect.h / 2.0 - 5.0, 20.0, WHITE);
-    }
-}
-
-struct SimulationLogger {
-    records: Vec<(u64, f32, f32, f32)>,
-}
-
-impl SimulationLogger {
-    fn new() -> Self {
-        Self {
-            records: Vec::new(),
-        }
-    }
-
-    fn record(&mut self, step: u64, temperature: f32, chem_potential: f32, density: f32) {
-        self.records
-            .push((step, temperature, chem_potential, density));
-    }
-
-    fn save_csv(&self, path: impl Into<PathBuf>) -> std::io::Result<()> {
-        let path: PathBuf = path.into();
-        let mut file = File::create(path)?;
-        writeln!(file, "step,temperature,chem_potential,density")?;
-        for (step, t, c, d) in &self.records {
-            writeln!(file, "{step},{t},{c},{d}")?;
-        }
-        Ok(())
-    }
-}
-
-fn calculate_ftc(d: f32, temp: f32, chem_potential: f32) -> f32 {
-    if d <= 0.0 || d >= 1.0 || temp <= 0.0 {
-        return -f32::INFINITY;
-    }
-    let energy_term = (J_MF * d * d + chem_potential * d) / temp;
-    let entropy_term = d * d.ln() + (1.0 - d) * (1.0 - d).ln();
-    energy_term - entropy_term
-}
-
-fn draw_ftc_plot(rect: Rect, temp: f32, chem_potential: f32, current_density: f32) {
+fn draw_ftc_plot(
+    rect: Rect,
+    model: MeanField,
+    temp: f32,
+    chem_potential: f32,
+    current_density: f32,
+    histogram: &DensityHistogram,
+) {
     let steps = 200;
     let mut points = Vec::new();
+    let mut qc_points = Vec::new();
     let mut max_f = -f32::INFINITY;
     let mut min_f = f32::INFINITY;
+    let MeanField { j, epsilon0, alpha } = model;
+    let quasi_chemical = QuasiChemical { j, epsilon0, alpha };
 
     for i in 0..=steps {
         let d = i as f32 / steps as f32;
-        let f = calculate_ftc(d, temp, chem_potential);
-        if f.is_finite() {
-            points.push((d, f));
-            if f > max_f {
-                max_f = f;
-            }
-            if f < min_f {
-                min_f = f;
+        let f = model.calculate_ftc(d, temp, chem_potential);
+        let f_qc = quasi_chemical.calculate_ftc(d, temp, chem_potential);
+        for (f, curve) in [(f, &mut points), (f_qc, &mut qc_points)] {
+            if f.is_finite() {
+                curve.push((d, f));
+                if f > max_f {
+                    max_f = f;
+                }
+                if f < min_f {
+                    min_f = f;
+                }
             }
         }
     }
@@ -303,18 +133,58 @@ fn draw_ftc_plot(rect: Rect, temp: f32, chem_potential: f32, current_density: f3
         WHITE,
     );
 
-    for i in 0..points.len() - 1 {
-        let (d1, f1) = points[i];
-        let (d2, f2) = points[i + 1];
-        let x1 = rect.x + d1 * rect.w;
-        let y1 = rect.y + rect.h - ((f1 - min_f) / (max_f - min_f).max(0.001)) * rect.h;
-        let x2 = rect.x + d2 * rect.w;
-        let y2 = rect.y + rect.h - ((f2 - min_f) / (max_f - min_f).max(0.001)) * rect.h;
-        draw_line(x1, y1, x2, y2, 2.0, YELLOW);
+    for (curve, color) in [(&qc_points, ORANGE), (&points, YELLOW)] {
+        for pair in curve.windows(2) {
+            let (d1, f1) = pair[0];
+            let (d2, f2) = pair[1];
+            let x1 = rect.x + d1 * rect.w;
+            let y1 = rect.y + rect.h - ((f1 - min_f) / (max_f - min_f).max(0.001)) * rect.h;
+            let x2 = rect.x + d2 * rect.w;
+            let y2 = rect.y + rect.h - ((f2 - min_f) / (max_f - min_f).max(0.001)) * rect.h;
+            draw_line(x1, y1, x2, y2, 2.0, color);
+        }
+    }
+
+    // Exact coexisting densities; the exact f_tc is flat between them.
+    if let Some((low, high)) = (Onsager { j, epsilon0, alpha }).binodal(temp as f64) {
+        for d in [low.density, high.density] {
+            let x = rect.x + d as f32 * rect.w;
+            draw_line(x, rect.y, x, rect.y + rect.h, 1.0, LIME);
+        }
     }
 
+    histogram.draw(rect, 200, (min_f, max_f), max_f);
+    draw_text(
+        "mean field f_tc",
+        rect.x + rect.w - 230.0,
+        rect.y + 16.0,
+        18.0,
+        YELLOW,
+    );
+    draw_text(
+        "quasi-chemical",
+        rect.x + rect.w - 230.0,
+        rect.y + 34.0,
+        18.0,
+        ORANGE,
+    );
+    draw_text(
+        "exact coexistence",
+        rect.x + rect.w - 230.0,
+        rect.y + 52.0,
+        18.0,
+        LIME,
+    );
+    draw_text(
+        &format!("MC ln P(d)/V, {} sweeps", histogram.samples()),
+        rect.x + rect.w - 230.0,
+        rect.y + 70.0,
+        18.0,
+        SKYBLUE,
+    );
+
     let marker_x = rect.x + current_density * rect.w;
-    let f_current = calculate_ftc(current_density, temp, chem_potential);
+    let f_current = model.calculate_ftc(current_density, temp, chem_potential);
     if f_current.is_finite() {
         let marker_y =
             rect.y + rect.h - ((f_current - min_f) / (max_f - min_f).max(0.001)) * rect.h;
@@ -322,50 +192,328 @@ fn draw_ftc_plot(rect: Rect, temp: f32, chem_potential: f32, current_density: f3
     }
 }
 
+/// Handles one of `session::VIEW_KEYS`.
+fn view_key(
+    key: KeyCode,
+    mode: &mut Mode,
+    time_series: &mut TimeSeriesPopup,
+    phase_diagram: &mut PhaseDiagram,
+) {
+    let tab_keys = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+    ];
+    if let Some(i) = tab_keys.iter().position(|&k| k == key) {
+        time_series.select(Observable::ALL[i]);
+    }
+    match key {
+        KeyCode::M => {
+            *mode = match mode {
+                Mode::UI => Mode::PhaseDiagram,
+                Mode::PhaseDiagram => Mode::FreeEnergyPlot,
+                Mode::FreeEnergyPlot => Mode::Clusters,
+                Mode::Clusters => Mode::StructureFactor,
+                Mode::StructureFactor => Mode::UI,
+            }
+        }
+        KeyCode::D => time_series.toggle(),
+        KeyCode::L => time_series.toggle_stacked(),
+        KeyCode::A if *mode == Mode::PhaseDiagram => phase_diagram.toggle_axes(),
+        KeyCode::G if *mode == Mode::PhaseDiagram => phase_diagram.cycle_map(),
+        _ => {}
+    }
+}
+
+fn new_lattice(settings: &Settings, seed: u64) -> Lattice {
+    Lattice::new_seeded(
+        settings.width,
+        settings.height,
+        settings.j,
+        settings.epsilon0,
+        settings.alpha,
+        settings.init_density,
+        seed,
+    )
+}
+
 #[macroquad::main("Lattice Simulator")]
 async fn main() {
-    let mut temperature: f32 = 0.7;
-    let mut chemical_potential: f32 = -1.0;
-    let mut lattice = Lattice::new(GRID_WIDTH, GRID_HEIGHT);
+    let mut settings = Settings {
+        temperature: 0.7,
+        chem_potential: -1.0,
+        j: J0,
+        epsilon0: 0.0,
+        alpha: 0.0,
+        width: GRID_WIDTH,
+        height: GRID_HEIGHT,
+        init_density: 0.5,
+        sweeps_per_frame: 1.0,
+        temperature_step: 0.01,
+        chem_potential_step: 0.02,
+        paused: false,
+    };
+    let mut pacer = Pacer::new();
+    let mut brush = Brush::new();
+    let mut lattice_view = LatticeView::new();
+    let mut gif: Option<(GifRecorder<BufWriter<File>>, PathBuf)> = None;
+    let mut origin = Origin {
+        seed: ::rand::random(),
+        step: 0,
+    };
+    let mut lattice = new_lattice(&settings, origin.seed);
+    let mut control_panel = ControlPanel::new(&settings);
     let mut mode = Mode::UI;
-    let mut logger = SimulationLogger::new();
+    let mut logger = SimulationLogger::with_limit(export::LOG_LIMIT);
+    let mut notice = Notice::new();
     let mut step_counter: u64 = 0;
-    let mut density_popup = DensityPopup::new(1000);
-
-    let phase_diagram = PhaseDiagram::new(100, 100, (0.01, 1.0), (-2.0, 0.0));
+    let mut time_series = TimeSeriesPopup::new(1000);
+    let mut conductance = Conductance::new();
+    let mut conductance_step: u64 = 0;
+    let mut cluster_view = ClusterView::new();
+    let mut structure_factor_panel = StructureFactorPanel::new(settings.width, settings.height);
+    let mut density_histogram = DensityHistogram::new();
+    let mut recorder: Option<Recorder> = None;
+    let mut replay: Option<Replay> = None;
+    // Rebuilds and view keys from this frame's input, applied (and recorded)
+    // together with replayed entries before the next sweep.
+    let mut pending: Vec<Entry> = Vec::new();
+
+    let mut phase_diagram = PhaseDiagram::new(
+        MeanField::from_lattice(&lattice),
+        100,
+        100,
+        (0.01, 1.0),
+        (-2.0, 0.0),
+    );
 
     loop {
-        if is_key_down(KeyCode::Up) {
-            temperature += 0.01;
-        }
-        if is_key_down(KeyCode::Down) {
-            temperature = (temperature - 0.01).max(0.01);
-        }
-        if is_key_down(KeyCode::Right) {
-            chemical_potential += 0.02;
-        }
-        if is_key_down(KeyCode::Left) {
-            chemical_potential -= 0.02;
+        let live = replay.is_none();
+        if !control_panel.is_mouse_over() && live {
+            if is_key_down(KeyCode::Up) {
+                settings.temperature += settings.temperature_step;
+            }
+            if is_key_down(KeyCode::Down) {
+                settings.temperature = (settings.temperature - settings.temperature_step).max(0.01);
+            }
+            if is_key_down(KeyCode::Right) {
+                settings.chem_potential += settings.chem_potential_step;
+            }
+            if is_key_down(KeyCode::Left) {
+                settings.chem_potential -= settings.chem_potential_step;
+            }
+            if is_key_pressed(KeyCode::Space) {
+                let rebuild = session::rebuild(&settings, ::rand::random());
+                pending.push(Entry::Action(rebuild));
+            }
         }
-        if is_key_pressed(KeyCode::Space) {
-            lattice = Lattice::new(GRID_WIDTH, GRID_HEIGHT);
+        if !control_panel.is_mouse_over() {
+            for key in session::VIEW_KEYS {
+                if is_key_pressed(key) {
+                    pending.push(Entry::Key(key));
+                }
+            }
+            if is_key_pressed(KeyCode::C) {
+                control_panel.toggle();
+            }
+            if is_key_pressed(KeyCode::B) {
+                brush.cycle_shape();
+            }
+            if is_key_pressed(KeyCode::LeftBracket) {
+                brush.resize(-1);
+            }
+            if is_key_pressed(KeyCode::RightBracket) {
+                brush.resize(1);
+            }
+            if is_key_pressed(KeyCode::Z) {
+                lattice_view.reset();
+            }
+            if is_key_pressed(KeyCode::P) {
+                settings.paused = !settings.paused;
+            }
+            if is_key_pressed(KeyCode::N) {
+                settings.paused = true;
+                pacer.single_step();
+            }
+            if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+                settings.scale_speed(2.0);
+            }
+            if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+                settings.scale_speed(0.5);
+            }
+            if is_key_pressed(KeyCode::S) {
+                let dir = control_panel.output_dir();
+                let saved = export::save_csv(&dir, &logger, &settings, origin, step_counter);
+                notice.report("Saved CSV", &saved);
+            }
+            if is_key_pressed(KeyCode::O) {
+                let saved = export::save_png(&control_panel.output_dir(), &lattice, step_counter);
+                notice.report("Saved PNG", &saved);
+            }
+            if is_key_pressed(KeyCode::R) {
+                gif = match gif.take() {
+                    Some((recorder, path)) => {
+                        let frames = recorder.frames();
+                        match recorder.finish() {
+                            Ok(()) => notice
+                                .success(format!("Saved GIF {} ({frames} frames)", path.display())),
+                            Err(e) => notice.failure(format!("GIF recording failed: {e}")),
+                        }
+                        None
+                    }
+                    None => {
+                        match export::start_gif(&control_panel.output_dir(), &lattice, step_counter)
+                        {
+                            Ok(started) => Some(started),
+                            Err(e) => {
+                                notice.failure(format!("GIF recording failed: {e}"));
+                                None
+                            }
+                        }
+                    }
+                };
+            }
+            if is_key_pressed(KeyCode::K) {
+                match recorder.take() {
+                    Some(session) => {
+                        let dir = control_panel.output_dir();
+                        let saved = session.finish(&settings, &dir, step_counter);
+                        if let Ok(path) = &saved {
+                            control_panel.set_session_path(path);
+                        }
+                        notice.report("Saved session", &saved);
+                    }
+                    None if live => {
+                        recorder = Some(Recorder::new(&settings, step_counter));
+                        let rebuild = session::rebuild(&settings, ::rand::random());
+                        pending.push(Entry::Action(rebuild));
+                    }
+                    None => notice.failure("Cannot record during a replay".to_string()),
+                }
+            }
+            if is_key_pressed(KeyCode::Y) {
+                if recorder.is_some() {
+                    notice.failure("Stop recording [K] before replaying".to_string());
+                } else {
+                    match Replay::load(&control_panel.session_path(), step_counter) {
+                        Ok(session) => replay = Some(session),
+                        Err(e) => notice.failure(format!("Replay failed: {e}")),
+                    }
+                }
+            }
         }
-        if is_key_pressed(KeyCode::M) {
-            mode = match mode {
-                Mode::UI => Mode::PhaseDiagram,
-                Mode::PhaseDiagram => Mode::FreeEnergyPlot,
-                Mode::FreeEnergyPlot => Mode::UI,
+
+        lattice.j = settings.j;
+        lattice.epsilon0 = settings.epsilon0;
+        lattice.alpha = settings.alpha;
+        let budget = pacer.sweeps(&settings);
+        let mut sweeps = 0;
+        let mut accepted = 0;
+        loop {
+            if let Some(session) = recorder.as_mut() {
+                session.sync(&settings, step_counter);
+            }
+            let mut due = std::mem::take(&mut pending);
+            while let Some(entry) = replay.as_mut().and_then(|r| r.next_due(step_counter)) {
+                due.push(entry);
+            }
+            let mut finished = false;
+            for entry in due {
+                match entry {
+                    Entry::Key(key) => {
+                        if let Some(session) = recorder.as_mut() {
+                            session.key(step_counter, key);
+                        }
+                        view_key(key, &mut mode, &mut time_series, &mut phase_diagram);
+                    }
+                    Entry::Action(Action::End) => finished = true,
+                    Entry::Action(action) => {
+                        if let Some(session) = recorder.as_mut() {
+                            session.action(&settings, step_counter, action);
+                        }
+                        session::apply(action, &mut settings, &mut lattice);
+                        if let Action::Rebuild { seed, .. } = action {
+                            origin = Origin {
+                                seed,
+                                step: step_counter,
+                            };
+                            structure_factor_panel.reset();
+                            density_histogram.reset();
+                        }
+                    }
+                }
+            }
+            if finished {
+                replay = None;
+                settings.paused = true;
+                notice.success("Replay finished".to_string());
+                break;
+            }
+            if sweeps == budget {
+                break;
+            }
+            sweeps += 1;
+            accepted += lattice.step(settings.temperature, settings.chem_potential);
+            step_counter += 1;
+            logger.record(
+                step_counter,
+                settings.temperature,
+                settings.chem_potential,
+                &lattice,
+            );
+            density_histogram.record(&lattice, settings.temperature, settings.chem_potential);
+            if step_counter.is_multiple_of(GIF_FRAME_SWEEPS)
+                && let Some((frames, _)) = gif.as_mut()
+                && let Err(e) = frames.add_frame(&lattice)
+            {
+                notice.failure(format!("GIF recording stopped: {e}"));
+                gif = None;
             }
         }
-        if is_key_pressed(KeyCode::D) {
-            density_popup.toggle();
+        let sites = (lattice.width * lattice.height) as f32;
+        let density = lattice.molecule_count() as f32 / sites;
+        if sweeps > 0 {
+            let sweep = step_counter;
+            time_series.record(Observable::Density, sweep, density);
+            time_series.record(Observable::Energy, sweep, lattice.energy() as f32 / sites);
+            time_series.record(
+                Observable::Acceptance,
+                sweep,
+                accepted as f32 / (sweeps as f32 * sites),
+            );
+            time_series.record(
+                Observable::Interface,
+                sweep,
+                lattice.interface_density() as f32,
+            );
+            if time_series.shows(Observable::LargestCluster) {
+                let largest = lattice.clusters(Boundary::Periodic).largest_size();
+                time_series.record(Observable::LargestCluster, sweep, largest as f32 / sites);
+            }
+            if time_series.shows(Observable::Conductance)
+                && sweep >= conductance_step + CONDUCTANCE_SWEEPS
+            {
+                conductance_step = sweep;
+                let g = conductance.measure(&lattice) as f32;
+                time_series.record(Observable::Conductance, sweep, g);
+            }
+            phase_diagram.record(settings.temperature, settings.chem_potential, density);
+            if mode == Mode::StructureFactor {
+                structure_factor_panel.update(
+                    &lattice,
+                    settings.temperature,
+                    settings.chem_potential,
+                );
+            }
         }
 
-        lattice.step(temperature, chemical_potential);
-        step_counter += 1;
-        let density = lattice.molecule_count() as f32 / (lattice.width * lattice.height) as f32;
-        logger.record(step_counter, temperature, chemical_potential, density);
-        density_popup.record_density(density);
+        let model = MeanField::from_lattice(&lattice);
+        if mode == Mode::PhaseDiagram && phase_diagram.model() != model {
+            phase_diagram.set_model(model);
+        }
 
         clear_background(BLACK);
 
@@ -386,22 +534,6 @@ async fn main() {
             main_panel_width - margin * 2.5,
             sh - margin * 2.0,
         );
-
-        lattice.draw(sim_rect);
-        draw_rectangle_lines(sim_rect.x, sim_rect.y, sim_rect.w, sim_rect.h, 2.0, GRAY);
-
-        if is_key_pressed(KeyCode::S) {
-            let filename = format!("simulation_{step_counter}_steps.csv");
-            let _ = logger.save_csv(filename);
-        }
-
-        match mode {
-            Mode::UI => draw_ui_panel(panel_rect, &lattice, temperature, chemical_potential),
-            Mode::PhaseDiagram => phase_diagram.draw(panel_rect, temperature, chemical_potential),
-            Mode::FreeEnergyPlot => {
-                draw_ftc_plot(panel_rect, temperature, chemical_potential, density);
-            }
-        }
         let desired_w = sw * 0.40;
         let desired_h = sh * 0.28;
         let popup_w = desired_w
@@ -414,13 +546,135 @@ async fn main() {
             .min(sh - 2.0 * margin);
         let popup_x = sw - margin - popup_w;
         let popup_y = sh - margin - popup_h;
-        density_popup.draw(Rect::new(popup_x, popup_y, popup_w, popup_h));
+        let popup_rect = Rect::new(popup_x, popup_y, popup_w, popup_h);
+
+        if !control_panel.is_mouse_over() {
+            time_series.handle_click(popup_rect);
+            lattice_view.handle_input(&lattice, sim_rect);
+            if !lattice_view.is_panning()
+                && live
+                && let Some(stroke) = brush.update(&mut lattice, &lattice_view, sim_rect)
+                && let Some(session) = recorder.as_mut()
+            {
+                session.action(&settings, step_counter, Action::Paint(stroke));
+            }
+        }
+        if mode == Mode::Clusters {
+            cluster_view.update(&lattice);
+            lattice_view.draw(&lattice, sim_rect, |x, y| cluster_view.site_color(x, y));
+        } else {
+            let (molecule, empty) = (phase_color_dark(), phase_color_bright());
+            lattice_view.draw(&lattice, sim_rect, |x, y| match lattice.grid[x][y] {
+                Site::Molecule => molecule,
+                Site::Empty => empty,
+            });
+        }
+        draw_rectangle_lines(sim_rect.x, sim_rect.y, sim_rect.w, sim_rect.h, 2.0, GRAY);
+        brush.draw_cursor(&lattice, &lattice_view, sim_rect);
+        if let Some((frames, _)) = &gif {
+            let label = format!("REC {} frames", frames.frames());
+            draw_text(
+                &label,
+                sim_rect.x + sim_rect.w - 150.0,
+                sim_rect.y + sim_rect.h - 8.0,
+                20.0,
+                RED,
+            );
+        }
+        if lattice_view.zoom() > 1.0 {
+            let label = format!("{:.1}x", lattice_view.zoom());
+            draw_text(
+                &label,
+                sim_rect.x + 8.0,
+                sim_rect.y + sim_rect.h - 8.0,
+                20.0,
+                ORANGE,
+            );
+        }
+        let session_label = match (&recorder, &replay) {
+            (Some(session), _) => Some(format!(
+                "REC session {} sweeps",
+                session.sweeps(step_counter)
+            )),
+            (_, Some(session)) => {
+                let (done, length) = session.progress(step_counter);
+                Some(format!("REPLAY {done} / {length} sweeps"))
+            }
+            _ => None,
+        };
+        if let Some(label) = session_label {
+            let dims = measure_text(&label, None, 20, 1.0);
+            draw_text(
+                &label,
+                sim_rect.x + sim_rect.w - dims.width - 8.0,
+                sim_rect.y + 20.0,
+                20.0,
+                RED,
+            );
+        }
+        notice.draw(sim_rect);
+
+        if control_panel.ui(
+            vec2(sim_rect.x + 10.0, sim_rect.y + 10.0),
+            &mut settings,
+            &mut pacer,
+            !live,
+        ) {
+            let rebuild = session::rebuild(&settings, ::rand::random());
+            pending.push(Entry::Action(rebuild));
+        }
+
+        if mode == Mode::PhaseDiagram
+            && live
+            && !control_panel.is_mouse_over()
+            && !time_series.is_mouse_over(popup_rect)
+            && is_mouse_button_down(MouseButton::Left)
+            && let Some((t, mu)) = phase_diagram.state_at(panel_rect, mouse_position().into())
+        {
+            settings.temperature = t;
+            settings.chem_potential = mu;
+        }
+
+        match mode {
+            Mode::UI => draw_ui_panel(panel_rect, &lattice, &settings, &brush, None),
+            Mode::PhaseDiagram => phase_diagram.draw(
+                panel_rect,
+                settings.temperature,
+                settings.chem_potential,
+                density,
+            ),
+            Mode::FreeEnergyPlot => {
+                draw_ftc_plot(
+                    panel_rect,
+                    model,
+                    settings.temperature,
+                    settings.chem_potential,
+                    density,
+                    &density_histogram,
+                );
+            }
+            Mode::Clusters => draw_ui_panel(
+                panel_rect,
+                &lattice,
+                &settings,
+                &brush,
+                Some(cluster_view.stats()),
+            ),
+            Mode::StructureFactor => structure_factor_panel.draw(panel_rect),
+        }
+        time_series.draw(popup_rect);
 
         next_frame().await
     }
 }
 
-fn draw_ui_panel(rect: Rect, lattice: &Lattice, temp: f32, chem_potential: f32) {
+fn draw_ui_panel(
+    rect: Rect,
+    lattice: &Lattice,
+    settings: &Settings,
+    brush: &Brush,
+    clusters: Option<&ClusterStats>,
+) {
     draw_rectangle(rect.x, rect.y, rect.w, rect.h, color_u8!(10, 10, 10, 200));
 
     let mut y_cursor = rect.y + 24.0;
@@ -452,14 +706,14 @@ fn draw_ui_panel(rect: Rect, lattice: &Lattice, temp: f32, chem_potential: f32)
     y_cursor += 28.0;
     row(
         "Temperature:",
-        &format!("{temp:.2}"),
+        &format!("{:.2}", settings.temperature),
         rect.x + 14.0,
         y_cursor,
     );
     y_cursor += 28.0;
     row(
         "Chem Potential:",
-        &format!("{chem_potential:.2}"),
+        &format!("{:.2}", settings.chem_potential),
         rect.x + 14.0,
         y_cursor,
     );
@@ -470,6 +724,77 @@ fn draw_ui_panel(rect: Rect, lattice: &Lattice, temp: f32, chem_potential: f32)
         rect.x + 14.0,
         y_cursor,
     );
+    y_cursor += 28.0;
+    let speed = if settings.paused {
+        "paused".to_string()
+    } else {
+        format!("{:.3} sweeps/frame", settings.sweeps_per_frame)
+    };
+    row("Speed:", &speed, rect.x + 14.0, y_cursor);
+    y_cursor += 28.0;
+    row(
+        "Brush:",
+        &format!("{}, radius {}", brush.shape.name(), brush.radius),
+        rect.x + 14.0,
+        y_cursor,
+    );
+    if let Some(stats) = clusters {
+        let n_sites = (lattice.width * lattice.height) as f32;
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        y_cursor += 28.0;
+        row(
+            "Clusters:",
+            &format!("{}", stats.count),
+            rect.x + 14.0,
+            y_cursor,
+        );
+        y_cursor += 28.0;
+        row(
+            "Largest Cluster:",
+            &format!(
+                "{} ({:.1}%)",
+                stats.largest,
+                100.0 * stats.largest as f32 / n_sites
+            ),
+            rect.x + 14.0,
+            y_cursor,
+        );
+        y_cursor += 28.0;
+        row(
+            "Largest Perimeter:",
+            &format!("{}", stats.largest_perimeter),
+            rect.x + 14.0,
+            y_cursor,
+        );
+        y_cursor += 28.0;
+        row(
+            "Boundary Dimension:",
+            &stats
+                .boundary_dimension
+                .map(|d| format!("{d:.3}"))
+                .unwrap_or_else(|| "-".into()),
+            rect.x + 14.0,
+            y_cursor,
+        );
+        y_cursor += 28.0;
+        row(
+            "Spans L-R / T-B:",
+            &format!(
+                "{} / {}",
+                yes_no(stats.percolation.spans_x),
+                yes_no(stats.percolation.spans_y)
+            ),
+            rect.x + 14.0,
+            y_cursor,
+        );
+        y_cursor += 28.0;
+        row(
+            "Wraps Torus:",
+            yes_no(stats.percolation.wraps_x || stats.percolation.wraps_y),
+            rect.x + 14.0,
+            y_cursor,
+        );
+    }
     y_cursor += 36.0;
     draw_line(
         rect.x + 12.0,
@@ -525,7 +850,70 @@ fn draw_ui_panel(rect: Rect, lattice: &Lattice, temp: f32, chem_potential: f32)
     );
     y_cursor += 25.0;
     draw_text_ex(
-        "[D] Toggle Density Popup",
+        "[D] Time Series  [1-6] Observable  [L] Stacked",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[A] Phase Diagram Axes",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[G] Phase Map: MF / MC Density / MC Variance",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[Click/Drag] Set T, µ on Phase Diagram",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[C] Toggle Parameter Panel",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[P] Pause  [N] Single Sweep  [+/-] Speed x2 / x0.5",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[Left/Right Drag] Paint / Erase  [B] Brush  [ [ ] ] Size",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[Wheel] Zoom  [Middle/Ctrl Drag] Pan  [Z] Reset View",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[O] Save PNG  [R] Start / Stop GIF Recording",
+        rect.x + 14.0,
+        y_cursor,
+        controls.clone(),
+    );
+    y_cursor += 25.0;
+    draw_text_ex(
+        "[K] Start / Stop Session Recording  [Y] Replay",
         rect.x + 14.0,
         y_cursor,
         controls.clone(),
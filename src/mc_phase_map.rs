@@ -0,0 +1,129 @@
+use crate::core::Lattice;
+use crate::mean_field::MeanField;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Side of the lattice simulated at every grid point.
+const SIZE: usize = 24;
+const EQUILIBRATION_SWEEPS: usize = 300;
+const MEASUREMENT_SWEEPS: usize = 300;
+
+#[derive(Clone, Copy)]
+pub struct McCell {
+    pub density: f32,
+    /// `V var(ρ)`, the density fluctuation per site.
+    pub variance: f32,
+}
+
+/// Grand-canonical Monte Carlo over a coarse `(T, µ)` grid, run on worker
+/// threads in the background. Cells fill in as their runs finish; dropping
+/// the map stops the workers.
+pub struct McPhaseMap {
+    resolution: (usize, usize),
+    temp_range: (f32, f32),
+    chem_potential_range: (f32, f32),
+    cells: Arc<Mutex<Vec<Option<McCell>>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl McPhaseMap {
+    pub fn start(
+        model: MeanField,
+        resolution: (usize, usize),
+        temp_range: (f32, f32),
+        chem_potential_range: (f32, f32),
+    ) -> Self {
+        let map = Self {
+            resolution,
+            temp_range,
+            chem_potential_range,
+            cells: Arc::new(Mutex::new(vec![None; resolution.0 * resolution.1])),
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        // Leave one core to the interactive simulation.
+        let workers =
+            thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1).max(1));
+        let points: Arc<Vec<(f32, f32)>> = Arc::new(
+            (0..resolution.0 * resolution.1)
+                .map(|index| map.state_point(index))
+                .collect(),
+        );
+        let next = Arc::new(AtomicUsize::new(0));
+        for _ in 0..workers {
+            let cells = Arc::clone(&map.cells);
+            let cancel = Arc::clone(&map.cancel);
+            let next = Arc::clone(&next);
+            let points = Arc::clone(&points);
+            thread::spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= points.len() || cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let (temp, chem_potential) = points[index];
+                    if let Some(cell) = simulate(model, temp, chem_potential, &cancel) {
+                        cells.lock().unwrap()[index] = Some(cell);
+                    }
+                }
+            });
+        }
+        map
+    }
+
+    /// `(T, µ)` at the centre of cell `index = i · res_µ + j`.
+    pub fn state_point(&self, index: usize) -> (f32, f32) {
+        let (res_t, res_c) = self.resolution;
+        let (i, j) = (index / res_c, index % res_c);
+        let centre =
+            |(a, b): (f32, f32), k: usize, n: usize| a + (k as f32 + 0.5) / n as f32 * (b - a);
+        (
+            centre(self.temp_range, i, res_t),
+            centre(self.chem_potential_range, j, res_c),
+        )
+    }
+
+    /// Finished cells so far, indexed `i · res_µ + j`.
+    pub fn cells(&self) -> Vec<Option<McCell>> {
+        self.cells.lock().unwrap().clone()
+    }
+}
+
+impl Drop for McPhaseMap {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn simulate(
+    model: MeanField,
+    temp: f32,
+    chem_potential: f32,
+    cancel: &AtomicBool,
+) -> Option<McCell> {
+    let mut lattice =
+        Lattice::new_with_params(SIZE, SIZE, model.j, model.epsilon0, model.alpha, 0.5);
+    for _ in 0..EQUILIBRATION_SWEEPS {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        lattice.step(temp, chem_potential);
+    }
+    let sites = (SIZE * SIZE) as f64;
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for _ in 0..MEASUREMENT_SWEEPS {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        lattice.step(temp, chem_potential);
+        let d = lattice.molecule_count() as f64 / sites;
+        sum += d;
+        sum_sq += d * d;
+    }
+    let mean = sum / MEASUREMENT_SWEEPS as f64;
+    let variance = (sum_sq / MEASUREMENT_SWEEPS as f64 - mean * mean).max(0.0);
+    Some(McCell {
+        density: mean as f32,
+        variance: (variance * sites) as f32,
+    })
+}
@@ -0,0 +1,274 @@
+use crate::core::Lattice;
+
+/// Coordination number of the square lattice.
+pub const Z: f32 = 4.0;
+
+/// A point of the mean-field phase diagram.
+#[derive(Clone, Copy)]
+pub struct StatePoint {
+    pub temperature: f64,
+    pub chem_potential: f64,
+    pub density: f64,
+}
+
+/// Phase boundaries of one approximation to the lattice gas.
+pub trait Theory {
+    fn name(&self) -> &'static str;
+
+    fn critical_point(&self) -> Option<StatePoint>;
+
+    /// Coexisting low- and high-density states below `T_c`.
+    fn binodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)>;
+
+    /// Limits of metastability below `T_c`, where `φ''(ρ) = 0`; `None` for
+    /// theories without a metastable branch.
+    fn spinodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)>;
+}
+
+/// Bragg–Williams mean field of the Hamiltonian used in `Lattice::step`,
+/// `H = −J·B + ε0·N − αN²/V − µN`, with the bond count replaced by its
+/// random-mixing value `B ≈ (z/2) N ρ`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct MeanField {
+    pub j: f32,
+    pub epsilon0: f32,
+    pub alpha: f32,
+}
+
+impl MeanField {
+    pub fn from_lattice(lattice: &Lattice) -> Self {
+        Self {
+            j: lattice.j,
+            epsilon0: lattice.epsilon0,
+            alpha: lattice.alpha,
+        }
+    }
+
+    /// Coefficient of `ρ²` in `−H/V`: `zJ/2 + α`.
+    pub fn j_mf(&self) -> f32 {
+        Z * self.j / 2.0 + self.alpha
+    }
+
+    /// `f_tc(ρ) = (J_MF ρ² + (µ − ε0) ρ) / T − [ρ ln ρ + (1 − ρ) ln(1 − ρ)]`,
+    /// i.e. `ln P(ρ)` per site, maximal at the stable density.
+    pub fn calculate_ftc(&self, d: f32, temp: f32, chem_potential: f32) -> f32 {
+        if d <= 0.0 || d >= 1.0 || temp <= 0.0 {
+            return -f32::INFINITY;
+        }
+        let energy_term = (self.j_mf() * d * d + (chem_potential - self.epsilon0) * d) / temp;
+        let entropy_term = d * d.ln() + (1.0 - d) * (1.0 - d).ln();
+        energy_term - entropy_term
+    }
+
+    /// `µ(ρ) = φ'(ρ)` with the Helmholtz free energy per site
+    /// `φ(ρ) = −J_MF ρ² + ε0 ρ + T [ρ ln ρ + (1 − ρ) ln(1 − ρ)]`,
+    /// at `ρ = 1 / (1 + e^−u)`.
+    fn chem_potential(&self, u: f64, temp: f64) -> f64 {
+        let d = logistic(u).0;
+        -2.0 * self.j_mf() as f64 * d + self.epsilon0 as f64 + temp * u
+    }
+
+    /// Stable density at `(T, µ)`.
+    #[allow(dead_code)] // GUI only
+    pub fn stable_density(&self, temp: f64, mu: f64) -> f64 {
+        self.density_at(temp, mu, self.binodal(temp))
+    }
+
+    /// Solution of `µ(ρ) = µ` at `temp`, with `binodal` the coexisting
+    /// states there. Below `T_c` the common tangent picks the branch: the
+    /// low-density one under the coexistence `µ`, the high-density one above.
+    #[allow(dead_code)] // GUI only
+    pub fn density_at(&self, temp: f64, mu: f64, binodal: Option<(StatePoint, StatePoint)>) -> f64 {
+        let (lo, hi) = match binodal {
+            Some((low, _)) if mu < low.chem_potential => (-1e6, logit(low.density)),
+            Some((_, high)) => (logit(high.density), 1e6),
+            None => (-1e6, 1e6),
+        };
+        logistic(invert(|u| self.chem_potential(u, temp), mu, lo, hi)).0
+    }
+
+    /// `φ − µρ` at `ρ = 1 / (1 + e^−u)`.
+    fn grand_potential(&self, u: f64, temp: f64, mu: f64) -> f64 {
+        let (d, ln_d, ln_1md) = logistic(u);
+        -(self.j_mf() as f64) * d * d
+            + (self.epsilon0 as f64 - mu) * d
+            + temp * (d * ln_d + (1.0 - d) * ln_1md)
+    }
+
+    fn state(&self, u: f64, temp: f64) -> StatePoint {
+        StatePoint {
+            temperature: temp,
+            chem_potential: self.chem_potential(u, temp),
+            density: logistic(u).0,
+        }
+    }
+}
+
+impl Theory for MeanField {
+    fn name(&self) -> &'static str {
+        "mean field"
+    }
+
+    /// `T_c = J_MF / 2` at `ρ = 1/2`; `None` without an attractive `J_MF`.
+    fn critical_point(&self) -> Option<StatePoint> {
+        let j_mf = self.j_mf() as f64;
+        (j_mf > 0.0).then(|| self.state(0.0, j_mf / 2.0))
+    }
+
+    /// Here `ρ(1 − ρ) = T / (2 J_MF)`.
+    fn spinodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)> {
+        let t_c = self.critical_point()?.temperature;
+        if temp <= 0.0 || temp >= t_c {
+            return None;
+        }
+        let half_width = 0.5 * (1.0 - temp / t_c).sqrt();
+        Some((
+            self.state(logit(0.5 - half_width), temp),
+            self.state(logit(0.5 + half_width), temp),
+        ))
+    }
+
+    fn binodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)> {
+        let (low, high) = self.spinodal(temp)?;
+        Some(common_tangent(
+            temp,
+            (logit(low.density), logit(high.density)),
+            |u| self.chem_potential(u, temp),
+            |u, mu| self.grand_potential(u, temp, mu),
+        ))
+    }
+}
+
+/// Coexisting states from the common-tangent construction: equal chemical
+/// potential and equal grand potential `φ − µρ` on the low- and high-density
+/// branches.
+///
+/// Densities enter as `u = ln(ρ / (1 − ρ))`, which stays resolved as `T → 0`
+/// where the coexisting densities approach 0 and 1 exponentially. `µ(u)` must
+/// rise on `u < u_spinodal.0` and on `u > u_spinodal.1`; coexistence lies
+/// between its local minimum at `u_spinodal.1` and maximum at `u_spinodal.0`.
+pub fn common_tangent(
+    temp: f64,
+    u_spinodal: (f64, f64),
+    chem_potential: impl Fn(f64) -> f64,
+    grand_potential: impl Fn(f64, f64) -> f64,
+) -> (StatePoint, StatePoint) {
+    let low_branch = |mu: f64| invert(&chem_potential, mu, -1e6, u_spinodal.0);
+    let high_branch = |mu: f64| invert(&chem_potential, mu, u_spinodal.1, 1e6);
+    // Δω(µ) = ω(ρ_high) − ω(ρ_low) falls with µ at rate −(ρ_high − ρ_low).
+    let (mut lo, mut hi) = (chem_potential(u_spinodal.1), chem_potential(u_spinodal.0));
+    for _ in 0..100 {
+        let mu = 0.5 * (lo + hi);
+        let delta = grand_potential(high_branch(mu), mu) - grand_potential(low_branch(mu), mu);
+        if delta > 0.0 {
+            lo = mu;
+        } else {
+            hi = mu;
+        }
+    }
+    let mu = 0.5 * (lo + hi);
+    let point = |u: f64| StatePoint {
+        temperature: temp,
+        chem_potential: mu,
+        density: logistic(u).0,
+    };
+    (point(low_branch(mu)), point(high_branch(mu)))
+}
+
+/// `u` in `[lo, hi]` with `chem_potential(u) = mu`, for `chem_potential`
+/// rising on that interval.
+fn invert(chem_potential: impl Fn(f64) -> f64, mu: f64, mut lo: f64, mut hi: f64) -> f64 {
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if chem_potential(mid) < mu {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+pub fn logit(d: f64) -> f64 {
+    (d / (1.0 - d)).ln()
+}
+
+/// `(ρ, ln ρ, ln(1 − ρ))` for `ρ = 1 / (1 + e^−u)`, without overflow.
+pub fn logistic(u: f64) -> (f64, f64, f64) {
+    let softplus = |x: f64| x.max(0.0) + (-x.abs()).exp().ln_1p();
+    let ln_d = -softplus(-u);
+    let ln_1md = -softplus(u);
+    (ln_d.exp(), ln_d, ln_1md)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: MeanField = MeanField {
+        j: 0.8,
+        epsilon0: 0.3,
+        alpha: 0.2,
+    };
+
+    #[test]
+    fn critical_temperature_is_half_j_mf() {
+        let critical = MODEL.critical_point().unwrap();
+        assert!((critical.temperature - MODEL.j_mf() as f64 / 2.0).abs() < 1e-6);
+        assert_eq!(critical.density, 0.5);
+    }
+
+    #[test]
+    fn binodal_sits_at_the_symmetric_chem_potential() {
+        let t_c = MODEL.critical_point().unwrap().temperature;
+        let mu = (MODEL.epsilon0 - Z * MODEL.j / 2.0 - MODEL.alpha) as f64;
+        for fraction in [0.2, 0.5, 0.8, 0.95, 0.999] {
+            let (low, high) = MODEL.binodal(fraction * t_c).unwrap();
+            assert!((low.chem_potential - mu).abs() < 1e-6, "T/Tc = {fraction}");
+            assert!((high.chem_potential - mu).abs() < 1e-6, "T/Tc = {fraction}");
+            assert!((low.density + high.density - 1.0).abs() < 1e-6);
+        }
+        assert!(MODEL.binodal(1.01 * t_c).is_none());
+    }
+
+    #[test]
+    fn spinodal_lies_inside_binodal() {
+        let t_c = MODEL.critical_point().unwrap().temperature;
+        for fraction in [0.2, 0.5, 0.8, 0.95] {
+            let (b_low, b_high) = MODEL.binodal(fraction * t_c).unwrap();
+            let (s_low, s_high) = MODEL.spinodal(fraction * t_c).unwrap();
+            assert!(b_low.density < s_low.density);
+            assert!(s_low.density < s_high.density);
+            assert!(s_high.density < b_high.density);
+        }
+    }
+
+    /// The density on a grid of 999 with the largest `f_tc`.
+    fn brute_force_density(model: &MeanField, temp: f32, chem_potential: f32) -> f32 {
+        (1..1000)
+            .map(|k| k as f32 / 1000.0)
+            .max_by(|&a, &b| {
+                let f = |d| model.calculate_ftc(d, temp, chem_potential);
+                f(a).total_cmp(&f(b))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn stable_density_maximises_ftc() {
+        let t_c = MODEL.critical_point().unwrap().temperature;
+        let mu_c = (MODEL.epsilon0 - Z * MODEL.j / 2.0 - MODEL.alpha) as f64;
+        for fraction in [0.3, 0.6, 0.85, 1.2, 2.0] {
+            let temp = fraction * t_c;
+            for k in -20..=20 {
+                let mu = mu_c + 0.05 * k as f64 + 0.01;
+                let expected = brute_force_density(&MODEL, temp as f32, mu as f32);
+                let d = MODEL.stable_density(temp, mu);
+                assert!(
+                    (d - expected as f64).abs() < 2e-3,
+                    "T = {temp}, µ = {mu}: {d} vs {expected}"
+                );
+            }
+        }
+    }
+}
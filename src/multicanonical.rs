@@ -0,0 +1,191 @@
+use crate::core::{Lattice, Site};
+use crate::wang_landau::FlatHistogram;
+use ::rand::Rng;
+
+/// Multicanonical sampling in the molecule count at fixed (T, µ).
+///
+/// Moves are the insertions/removals of `Lattice::step`, accepted with the
+/// grand-canonical weight times `exp(η(N))`. The weights are built
+/// Wang–Landau style: every visit lowers `η(N)` by `ln f`, and `ln f` is
+/// halved each time the visit histogram is flat. Once frozen, the biased walk
+/// crosses the coexistence barrier freely and the unbiased distribution is
+/// `ln P(N) = ln H(N) − η(N)`.
+pub struct Multicanonical {
+    pub lattice: Lattice,
+    pub temperature: f32,
+    pub chem_potential: f32,
+    n_range: (usize, usize),
+    pub ln_f: f64,
+    pub flatness: f64,
+    /// `η(N)` for `N` in `n_range.0..=n_range.1`.
+    weights: Vec<f64>,
+    histogram: Vec<u64>,
+}
+
+impl Multicanonical {
+    /// The weights cover the molecule counts `n_range`, which the lattice
+    /// enters through `Lattice::enter_n_range`.
+    pub fn new(
+        mut lattice: Lattice,
+        temperature: f32,
+        chem_potential: f32,
+        n_range: (usize, usize),
+        flatness: f64,
+    ) -> Self {
+        let n_range = lattice.enter_n_range(n_range);
+        let bins = n_range.1 - n_range.0 + 1;
+        Self {
+            lattice,
+            temperature,
+            chem_potential,
+            n_range,
+            ln_f: 1.0,
+            flatness,
+            weights: vec![0.0; bins],
+            histogram: vec![0; bins],
+        }
+    }
+
+    fn bin(&self, n: usize) -> usize {
+        n - self.n_range.0
+    }
+
+    /// Fixes the weights for production: `ln f = 0` and a cleared histogram.
+    pub fn freeze(&mut self) {
+        self.histogram.fill(0);
+        self.ln_f = 0.0;
+    }
+
+    /// `(N, ln P(N), visits)` for every `N` visited since `freeze`,
+    /// with the most probable count at `ln P = 0`.
+    pub fn ln_p(&self) -> Vec<(usize, f64, u64)> {
+        let mut profile: Vec<(usize, f64, u64)> = self
+            .histogram
+            .iter()
+            .zip(&self.weights)
+            .enumerate()
+            .filter(|&(_, (&count, _))| count > 0)
+            .map(|(i, (&count, &eta))| (self.n_range.0 + i, (count as f64).ln() - eta, count))
+            .collect();
+        let max = profile
+            .iter()
+            .map(|p| p.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        for p in profile.iter_mut() {
+            p.1 -= max;
+        }
+        profile
+    }
+}
+
+impl FlatHistogram for Multicanonical {
+    /// The molecule count is recorded, and its weight lowered by `ln f`,
+    /// after every attempt.
+    fn sweep(&mut self) {
+        if self.temperature <= 0.0 {
+            return;
+        }
+        let (w, h) = (self.lattice.width, self.lattice.height);
+        let beta = 1.0 / self.temperature as f64;
+        for _ in 0..(w * h) {
+            let x = self.lattice.rng().random_range(0..w);
+            let y = self.lattice.rng().random_range(0..h);
+            let n = self.lattice.molecule_count();
+            let n_new = match self.lattice.grid[x][y] {
+                Site::Empty => n + 1,
+                Site::Molecule => n - 1,
+            };
+
+            if (self.n_range.0..=self.n_range.1).contains(&n_new) {
+                let delta_h = self.lattice.delta_h(x, y, self.chem_potential) as f64;
+                let ln_accept =
+                    -beta * delta_h + self.weights[self.bin(n_new)] - self.weights[self.bin(n)];
+                if ln_accept >= 0.0 || self.lattice.rng().random::<f64>() < ln_accept.exp() {
+                    self.lattice.flip(x, y);
+                }
+            }
+
+            let bin = self.bin(self.lattice.molecule_count());
+            self.weights[bin] -= self.ln_f;
+            self.histogram[bin] += 1;
+        }
+    }
+
+    /// Every `N` in the window has at least `flatness` times the mean count.
+    fn is_flat(&self) -> bool {
+        let mean = self.histogram.iter().sum::<u64>() as f64 / self.histogram.len() as f64;
+        let min = self.histogram.iter().copied().min().unwrap_or(0) as f64;
+        mean > 0.0 && min >= self.flatness * mean
+    }
+
+    fn refine(&mut self) {
+        self.histogram.fill(0);
+        self.ln_f /= 2.0;
+    }
+
+    fn ln_f(&self) -> f64 {
+        self.ln_f
+    }
+
+    /// Molecule counts whose weight has been lowered.
+    fn macrostates(&self) -> usize {
+        self.weights.iter().filter(|&&eta| eta != 0.0).count()
+    }
+}
+
+/// Free-energy barrier `β ΔF` of a profile `ln P`: the largest drop from the
+/// lower of the maxima on either side of a point down to that point. `None`
+/// if the profile has a single peak.
+pub fn barrier(ln_p: &[f64]) -> Option<f64> {
+    let n = ln_p.len();
+    let mut left = vec![f64::NEG_INFINITY; n];
+    let mut right = vec![f64::NEG_INFINITY; n];
+    for i in 1..n {
+        left[i] = left[i - 1].max(ln_p[i - 1]);
+    }
+    for i in (0..n.saturating_sub(1)).rev() {
+        right[i] = right[i + 1].max(ln_p[i + 1]);
+    }
+    (0..n)
+        .map(|i| left[i].min(right[i]) - ln_p[i])
+        .filter(|b| b.is_finite() && *b > 0.0)
+        .reduce(f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn barrier_is_the_drop_below_the_lower_peak() {
+        let ln_p = [-1.0, 0.0, -1.0, -3.0, -2.0, -0.5, -4.0];
+        assert_eq!(barrier(&ln_p), Some(2.5));
+        assert_eq!(barrier(&[-2.0, -1.0, 0.0, -1.0]), None);
+        assert_eq!(barrier(&[]), None);
+    }
+
+    #[test]
+    fn profile_removes_the_weights_from_the_visits() {
+        let lattice = Lattice::new_seeded(4, 4, 1.0, 0.0, 0.0, 0.5, 1);
+        let mut muca = Multicanonical::new(lattice, 1.0, 0.0, (2, 6), 0.8);
+        muca.weights = vec![0.0, -1.0, -2.5, -1.0, 0.5];
+        muca.histogram = vec![10, 20, 0, 40, 5];
+        let profile = muca.ln_p();
+
+        let expected = [
+            (2, 10f64.ln()),
+            (3, 20f64.ln() + 1.0),
+            (5, 40f64.ln() + 1.0),
+            (6, 5f64.ln() - 0.5),
+        ];
+        let max = expected
+            .iter()
+            .map(|e| e.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(profile.len(), expected.len());
+        for (&(n, ln_p, _), &(m, value)) in profile.iter().zip(&expected) {
+            assert_eq!(n, m);
+            assert!((ln_p - (value - max)).abs() < 1e-12);
+        }
+    }
+}
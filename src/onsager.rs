@@ -0,0 +1,93 @@
+use crate::mean_field::{StatePoint, Theory, Z};
+use std::f64::consts::SQRT_2;
+
+/// Exact results for the square-lattice gas through its mapping onto the
+/// Ising model with coupling `J/4` and field `(µ − ε0 + zJ/2) / 2`:
+/// Onsager's critical temperature and Yang's spontaneous magnetisation
+/// `m₀ = (1 − sinh⁻⁴(J / 2T))^{1/8}`, giving coexisting densities `(1 ∓ m₀) / 2`
+/// at `µ = ε0 − zJ/2`.
+///
+/// Only the short-range model is exactly solved: with `α ≠ 0` every
+/// boundary is `None`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Onsager {
+    pub j: f32,
+    pub epsilon0: f32,
+    pub alpha: f32,
+}
+
+impl Onsager {
+    fn coexistence_chem_potential(&self) -> f64 {
+        self.epsilon0 as f64 - Z as f64 * self.j as f64 / 2.0
+    }
+}
+
+impl Theory for Onsager {
+    fn name(&self) -> &'static str {
+        "exact (Onsager/Yang)"
+    }
+
+    /// `T_c = J / (2 ln(1 + √2)) ≈ 0.567 J`.
+    fn critical_point(&self) -> Option<StatePoint> {
+        (self.alpha == 0.0 && self.j > 0.0).then(|| StatePoint {
+            temperature: self.j as f64 / (2.0 * (1.0 + SQRT_2).ln()),
+            chem_potential: self.coexistence_chem_potential(),
+            density: 0.5,
+        })
+    }
+
+    fn binodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)> {
+        let t_c = self.critical_point()?.temperature;
+        if temp <= 0.0 || temp >= t_c {
+            return None;
+        }
+        let m0 = (1.0 - (self.j as f64 / (2.0 * temp)).sinh().powi(-4)).powf(0.125);
+        let point = |d: f64| StatePoint {
+            temperature: temp,
+            chem_potential: self.coexistence_chem_potential(),
+            density: d,
+        };
+        Some((point((1.0 - m0) / 2.0), point((1.0 + m0) / 2.0)))
+    }
+
+    /// The exact equilibrium free energy is convex; there is no spinodal.
+    fn spinodal(&self, _temp: f64) -> Option<(StatePoint, StatePoint)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: Onsager = Onsager {
+        j: 1.5,
+        epsilon0: 0.2,
+        alpha: 0.0,
+    };
+
+    #[test]
+    fn critical_temperature_is_onsagers() {
+        let t_c = MODEL.critical_point().unwrap().temperature;
+        assert!((t_c - 1.5 / (2.0 * (1.0 + SQRT_2).ln())).abs() < 1e-12);
+        let long_range = Onsager {
+            alpha: 0.1,
+            ..MODEL
+        };
+        assert!(long_range.critical_point().is_none());
+    }
+
+    #[test]
+    fn binodal_is_symmetric_at_the_coexistence_chem_potential() {
+        let t_c = MODEL.critical_point().unwrap().temperature;
+        let mu = (MODEL.epsilon0 - Z * MODEL.j / 2.0) as f64;
+        for fraction in [0.2, 0.6, 0.95, 0.999] {
+            let (low, high) = MODEL.binodal(fraction * t_c).unwrap();
+            assert!((low.chem_potential - mu).abs() < 1e-6);
+            assert!((high.chem_potential - mu).abs() < 1e-6);
+            assert!((low.density + high.density - 1.0).abs() < 1e-12);
+            assert!(low.density < 0.5);
+        }
+        assert!(MODEL.binodal(1.01 * t_c).is_none());
+    }
+}
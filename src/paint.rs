@@ -0,0 +1,67 @@
+use crate::core::{Lattice, Site};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BrushShape {
+    Disk,
+    Square,
+    /// Press sets one end, release the other; the segment is drawn with the
+    /// disk brush.
+    Line,
+}
+
+impl BrushShape {
+    pub const ALL: [BrushShape; 3] = [BrushShape::Disk, BrushShape::Square, BrushShape::Line];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BrushShape::Disk => "disk",
+            BrushShape::Square => "square",
+            BrushShape::Line => "line",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|shape| shape.name() == name)
+    }
+}
+
+/// One brush application: `site` stamped at every site along the segment
+/// `from`–`to`. Stamps wrap around the periodic boundaries and go through
+/// `Lattice::set_site`, so `num_molecules` stays consistent.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub site: Site,
+    pub shape: BrushShape,
+    /// Sites within this distance of the centre are painted; 0 paints one site.
+    pub radius: i64,
+    pub from: (i64, i64),
+    pub to: (i64, i64),
+}
+
+impl Stroke {
+    pub fn apply(&self, lattice: &mut Lattice) {
+        let (from, to) = (self.from, self.to);
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).max(1);
+        for k in 0..=steps {
+            let t = k as f32 / steps as f32;
+            let x = from.0 + ((to.0 - from.0) as f32 * t).round() as i64;
+            let y = from.1 + ((to.1 - from.1) as f32 * t).round() as i64;
+            self.stamp(lattice, (x, y));
+        }
+    }
+
+    fn stamp(&self, lattice: &mut Lattice, (cx, cy): (i64, i64)) {
+        let r = self.radius;
+        let (w, h) = (lattice.width as i64, lattice.height as i64);
+        for dx in -r..=r {
+            for dy in -r..=r {
+                if self.shape != BrushShape::Square && dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                let x = (cx + dx).rem_euclid(w) as usize;
+                let y = (cy + dy).rem_euclid(h) as usize;
+                lattice.set_site(x, y, self.site);
+            }
+        }
+    }
+}
@@ -0,0 +1,542 @@
+use crate::mc_phase_map::{McCell, McPhaseMap};
+use crate::mean_field::{MeanField, StatePoint, Theory};
+use crate::onsager::Onsager;
+use crate::plot::{Axes, format_tick, nice_ticks};
+use crate::quasi_chemical::QuasiChemical;
+use crate::{phase_color_bright, phase_color_dark};
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+
+const TRAJECTORY_LEN: usize = 2000;
+/// `(T, µ)` cells of the background Monte Carlo map.
+const MC_RESOLUTION: (usize, usize) = (24, 24);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PhaseAxes {
+    /// Temperature across, chemical potential up.
+    TemperatureChemPotential,
+    /// Density across, temperature up.
+    DensityTemperature,
+}
+
+/// Quantity shown as the background of the diagram.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PhaseMap {
+    MeanField,
+    McDensity,
+    McVariance,
+}
+
+/// Phase boundaries of one theory, sampled on the temperature axis of the
+/// phase diagram.
+struct Boundaries {
+    name: &'static str,
+    color: Color,
+    binodal: Vec<(StatePoint, StatePoint)>,
+    spinodal: Vec<(StatePoint, StatePoint)>,
+    critical_point: Option<StatePoint>,
+}
+
+impl Boundaries {
+    fn new(theory: &dyn Theory, temps: &[f64], color: Color) -> Self {
+        Self {
+            name: theory.name(),
+            color,
+            binodal: temps.iter().filter_map(|&t| theory.binodal(t)).collect(),
+            spinodal: temps.iter().filter_map(|&t| theory.spinodal(t)).collect(),
+            critical_point: theory.critical_point(),
+        }
+    }
+}
+
+/// Dark blue through yellow for `fraction` in `[0, 1]`.
+fn variance_color(fraction: f32) -> Color {
+    let f = fraction.clamp(0.0, 1.0);
+    Color::new(0.08 + 0.92 * f, 0.08 + 0.82 * f, 0.25 - 0.15 * f, 1.0)
+}
+
+fn density_color(density: f32) -> Color {
+    let bright = phase_color_bright();
+    let dark = phase_color_dark();
+    let d = density.clamp(0.0, 1.0);
+    Color {
+        r: bright.r + d * (dark.r - bright.r),
+        g: bright.g + d * (dark.g - bright.g),
+        b: bright.b + d * (dark.b - bright.b),
+        a: 1.0,
+    }
+}
+
+/// Mean-field chemical potential of a homogeneous state at density `d`,
+/// `µ = ε0 − 2 J_MF ρ + T ln(ρ / (1 − ρ))`, or the coexistence value where
+/// `d` lies between the binodal densities.
+fn equilibrium_chem_potential(model: &MeanField, d: f32, temp: f32) -> f32 {
+    let d = d.clamp(1e-6, 1.0 - 1e-6);
+    if let Some((low, high)) = model.binodal(temp as f64)
+        && (d as f64) > low.density
+        && (d as f64) < high.density
+    {
+        return low.chem_potential as f32;
+    }
+    model.epsilon0 - 2.0 * model.j_mf() * d + temp * (d / (1.0 - d)).ln()
+}
+
+/// Mean-field phase map with the boundaries of every theory and the recent
+/// path of the simulation, in the `(T, µ)` or `(ρ, T)` plane.
+pub struct PhaseDiagram {
+    model: MeanField,
+    densities: Vec<Vec<f32>>,
+    /// Mean-field coexisting densities for every temperature row.
+    coexistence: Vec<Option<(f32, f32)>>,
+    boundaries: Vec<Boundaries>,
+    temp_range: (f32, f32),
+    chem_potential_range: (f32, f32),
+    resolution: (usize, usize),
+    axes: PhaseAxes,
+    map: PhaseMap,
+    /// Background Monte Carlo run for the current model, started on demand.
+    mc: Option<McPhaseMap>,
+    /// Recent `(T, µ, ρ)` of the running simulation, oldest first.
+    trajectory: VecDeque<(f32, f32, f32)>,
+}
+
+impl PhaseDiagram {
+    pub fn new(
+        model: MeanField,
+        resolution_t: usize,
+        resolution_c: usize,
+        temp_range: (f32, f32),
+        chem_potential_range: (f32, f32),
+    ) -> Self {
+        let mut diagram = Self {
+            model,
+            densities: Vec::new(),
+            coexistence: Vec::new(),
+            boundaries: Vec::new(),
+            temp_range,
+            chem_potential_range,
+            resolution: (resolution_t, resolution_c),
+            axes: PhaseAxes::TemperatureChemPotential,
+            map: PhaseMap::MeanField,
+            mc: None,
+            trajectory: VecDeque::with_capacity(TRAJECTORY_LEN),
+        };
+        diagram.set_model(model);
+        diagram
+    }
+
+    pub fn model(&self) -> MeanField {
+        self.model
+    }
+
+    fn temperature(&self, i: usize) -> f32 {
+        let (t0, t1) = self.temp_range;
+        t0 + (i as f32 / self.resolution.0 as f32) * (t1 - t0)
+    }
+
+    fn chem_potential(&self, j: usize) -> f32 {
+        let (c0, c1) = self.chem_potential_range;
+        c0 + (j as f32 / self.resolution.1 as f32) * (c1 - c0)
+    }
+
+    /// Recomputes the maps and boundaries for new model parameters, keeping
+    /// the view and the trajectory.
+    pub fn set_model(&mut self, model: MeanField) {
+        let (res_t, res_c) = self.resolution;
+        self.model = model;
+        let binodal: Vec<_> = (0..res_t)
+            .map(|i| model.binodal(self.temperature(i) as f64))
+            .collect();
+        self.densities = binodal
+            .iter()
+            .enumerate()
+            .map(|(i, &coexisting)| {
+                let temp = self.temperature(i) as f64;
+                (0..res_c)
+                    .map(|j| {
+                        let chem_potential = self.chem_potential(j) as f64;
+                        model.density_at(temp, chem_potential, coexisting) as f32
+                    })
+                    .collect()
+            })
+            .collect();
+        self.coexistence = binodal
+            .iter()
+            .map(|b| b.map(|(low, high)| (low.density as f32, high.density as f32)))
+            .collect();
+
+        let temps: Vec<f64> = (0..=res_t).map(|i| self.temperature(i) as f64).collect();
+        let MeanField { j, epsilon0, alpha } = model;
+        self.boundaries = vec![
+            Boundaries::new(&model, &temps, WHITE),
+            Boundaries::new(&QuasiChemical { j, epsilon0, alpha }, &temps, ORANGE),
+            Boundaries::new(&Onsager { j, epsilon0, alpha }, &temps, LIME),
+        ];
+        self.mc = (self.map != PhaseMap::MeanField).then(|| self.start_mc());
+    }
+
+    fn start_mc(&self) -> McPhaseMap {
+        McPhaseMap::start(
+            self.model,
+            MC_RESOLUTION,
+            self.temp_range,
+            self.chem_potential_range,
+        )
+    }
+
+    /// Cycles the background through mean field, MC density and MC variance,
+    /// starting the Monte Carlo run the first time it is needed.
+    pub fn cycle_map(&mut self) {
+        self.map = match self.map {
+            PhaseMap::MeanField => PhaseMap::McDensity,
+            PhaseMap::McDensity => PhaseMap::McVariance,
+            PhaseMap::McVariance => PhaseMap::MeanField,
+        };
+        if self.map != PhaseMap::MeanField && self.mc.is_none() {
+            self.mc = Some(self.start_mc());
+        }
+    }
+
+    pub fn toggle_axes(&mut self) {
+        self.axes = match self.axes {
+            PhaseAxes::TemperatureChemPotential => PhaseAxes::DensityTemperature,
+            PhaseAxes::DensityTemperature => PhaseAxes::TemperatureChemPotential,
+        };
+    }
+
+    pub fn record(&mut self, temp: f32, chem_potential: f32, density: f32) {
+        if self.trajectory.len() == TRAJECTORY_LEN {
+            self.trajectory.pop_front();
+        }
+        self.trajectory.push_back((temp, chem_potential, density));
+    }
+
+    /// Plot coordinates of a state in the current axes.
+    fn point(&self, temp: f32, chem_potential: f32, density: f32) -> (f32, f32) {
+        match self.axes {
+            PhaseAxes::TemperatureChemPotential => (temp, chem_potential),
+            PhaseAxes::DensityTemperature => (density, temp),
+        }
+    }
+
+    fn plot_axes(&self, rect: Rect) -> Axes {
+        match self.axes {
+            PhaseAxes::TemperatureChemPotential => {
+                Axes::new(rect, self.temp_range, self.chem_potential_range)
+            }
+            PhaseAxes::DensityTemperature => Axes::new(rect, (0.0, 1.0), self.temp_range),
+        }
+    }
+
+    /// Plot area inside the panel `rect`, leaving room for ticks and the
+    /// colour bar.
+    fn plot_rect(rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + 60.0,
+            rect.y + 30.0,
+            (rect.w - 150.0).max(10.0),
+            (rect.h - 80.0).max(10.0),
+        )
+    }
+
+    /// `(T, µ)` under the screen point `p` of the panel `rect`, or `None`
+    /// outside the plot. In the `(ρ, T)` plane `µ` is the mean-field
+    /// equilibrium chemical potential of that density.
+    pub fn state_at(&self, rect: Rect, p: Vec2) -> Option<(f32, f32)> {
+        let axes = self.plot_axes(Self::plot_rect(rect));
+        let (x, y) = axes.to_data(p);
+        if !axes.contains(x, y) {
+            return None;
+        }
+        Some(match self.axes {
+            PhaseAxes::TemperatureChemPotential => (x.max(0.01), y),
+            PhaseAxes::DensityTemperature => {
+                let temp = y.max(0.01);
+                (temp, equilibrium_chem_potential(&self.model, x, temp))
+            }
+        })
+    }
+
+    pub fn draw(&self, rect: Rect, current_temp: f32, current_chem_potential: f32, density: f32) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color_u8!(10, 10, 10, 200));
+        let plot_rect = Self::plot_rect(rect);
+        let axes = self.plot_axes(plot_rect);
+        let cells = match (self.map, &self.mc) {
+            (PhaseMap::MeanField, _) | (_, None) => None,
+            (_, Some(mc)) => Some(mc.cells()),
+        };
+        let max_variance = cells
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|c| c.variance)
+            .fold(0.0, f32::max);
+
+        self.draw_map(&axes, cells.as_deref(), max_variance);
+        self.draw_boundaries(&axes);
+        self.draw_trajectory(&axes);
+
+        let (x, y) = self.point(current_temp, current_chem_potential, density);
+        if axes.contains(x, y) {
+            let p = axes.to_screen(x, y);
+            draw_circle(p.x, p.y, 5.0, RED);
+        }
+
+        match self.axes {
+            PhaseAxes::TemperatureChemPotential => axes.draw("T", "µ"),
+            PhaseAxes::DensityTemperature => axes.draw("ρ", "T"),
+        }
+        let bar = Rect::new(
+            plot_rect.x + plot_rect.w + 24.0,
+            plot_rect.y,
+            16.0,
+            plot_rect.h,
+        );
+        if self.map == PhaseMap::McVariance {
+            draw_colour_bar(bar, "V var ρ", max_variance.max(1e-3), variance_color);
+        } else {
+            draw_colour_bar(bar, "ρ", 1.0, density_color);
+        }
+
+        let status = match &cells {
+            None => "mean-field map".to_string(),
+            Some(cells) => {
+                let done = cells.iter().flatten().count();
+                let label = match self.map {
+                    PhaseMap::McVariance => "MC variance",
+                    _ => "MC density",
+                };
+                format!("{label}: {done}/{} cells", cells.len())
+            }
+        };
+        let dims = measure_text(&status, None, 18, 1.0);
+        draw_text(
+            &status,
+            plot_rect.x + plot_rect.w - dims.width,
+            rect.y + 20.0,
+            18.0,
+            LIGHTGRAY,
+        );
+
+        let mouse: Vec2 = mouse_position().into();
+        if let Some((temp, chem_potential)) = self.state_at(rect, mouse) {
+            let text = format!(
+                "T = {temp:.3}  µ = {chem_potential:.3}  ρ_MF = {:.3}",
+                self.model
+                    .stable_density(temp as f64, chem_potential as f64)
+            );
+            let dims = measure_text(&text, None, 18, 1.0);
+            let x = (mouse.x + 14.0).min(plot_rect.x + plot_rect.w - dims.width - 6.0);
+            let y = (mouse.y - 12.0).max(plot_rect.y + 20.0);
+            draw_rectangle(
+                x - 4.0,
+                y - dims.offset_y - 3.0,
+                dims.width + 8.0,
+                dims.height + 6.0,
+                color_u8!(10, 10, 10, 220),
+            );
+            draw_text(&text, x, y, 18.0, WHITE);
+        }
+    }
+
+    /// Stable mean-field density, or the Monte Carlo cells when `cells` is
+    /// given, in the `(T, µ)` plane. In the `(ρ, T)` plane the density itself,
+    /// shaded inside the mean-field coexistence region, with the measured
+    /// Monte Carlo densities as dots.
+    fn draw_map(&self, axes: &Axes, cells: Option<&[Option<McCell>]>, max_variance: f32) {
+        let rect = axes.rect;
+        let (res_t, res_c) = self.resolution;
+        let cell_color = |cell: &McCell| match self.map {
+            PhaseMap::McVariance => variance_color(cell.variance / max_variance),
+            _ => density_color(cell.density),
+        };
+        match self.axes {
+            PhaseAxes::TemperatureChemPotential if let Some(cells) = cells => {
+                let (mc_t, mc_c) = MC_RESOLUTION;
+                let cell_w = rect.w / mc_t as f32;
+                let cell_h = rect.h / mc_c as f32;
+                for (index, cell) in cells.iter().enumerate() {
+                    let (i, j) = (index / mc_c, index % mc_c);
+                    draw_rectangle(
+                        rect.x + i as f32 * cell_w,
+                        rect.y + rect.h - (j + 1) as f32 * cell_h,
+                        cell_w,
+                        cell_h,
+                        cell.as_ref().map_or(DARKGRAY, cell_color),
+                    );
+                }
+            }
+            PhaseAxes::TemperatureChemPotential => {
+                let cell_w = rect.w / res_t as f32;
+                let cell_h = rect.h / res_c as f32;
+                for i in 0..res_t {
+                    for j in 0..res_c {
+                        draw_rectangle(
+                            rect.x + i as f32 * cell_w,
+                            rect.y + rect.h - (j + 1) as f32 * cell_h,
+                            cell_w,
+                            cell_h,
+                            density_color(self.densities[i][j]),
+                        );
+                    }
+                }
+            }
+            PhaseAxes::DensityTemperature => {
+                let cell_w = rect.w / res_c as f32;
+                let cell_h = rect.h / res_t as f32;
+                for i in 0..res_t {
+                    for k in 0..res_c {
+                        let d = (k as f32 + 0.5) / res_c as f32;
+                        let mut color = density_color(d);
+                        if let Some((low, high)) = self.coexistence[i]
+                            && d > low
+                            && d < high
+                        {
+                            color = Color::new(color.r * 0.6, color.g * 0.6, color.b * 0.8, 1.0);
+                        }
+                        draw_rectangle(
+                            rect.x + k as f32 * cell_w,
+                            rect.y + rect.h - (i + 1) as f32 * cell_h,
+                            cell_w,
+                            cell_h,
+                            color,
+                        );
+                    }
+                }
+                if let (Some(cells), Some(mc)) = (cells, &self.mc) {
+                    for (index, cell) in cells.iter().enumerate() {
+                        let Some(cell) = cell else { continue };
+                        let (temp, _) = mc.state_point(index);
+                        let p = axes.to_screen(cell.density, temp);
+                        let color = match self.map {
+                            PhaseMap::McVariance => cell_color(cell),
+                            _ => WHITE,
+                        };
+                        draw_circle(p.x, p.y, 2.5, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Binodal (thick), spinodals (thin) and critical point of every theory,
+    /// with a legend.
+    fn draw_boundaries(&self, axes: &Axes) {
+        let polyline = |points: &[StatePoint], thickness: f32, color: Color| {
+            let screen: Vec<Option<Vec2>> = points
+                .iter()
+                .map(|p| {
+                    let (x, y) = self.point(
+                        p.temperature as f32,
+                        p.chem_potential as f32,
+                        p.density as f32,
+                    );
+                    axes.contains(x, y).then(|| axes.to_screen(x, y))
+                })
+                .collect();
+            for pair in screen.windows(2) {
+                if let (Some(a), Some(b)) = (pair[0], pair[1]) {
+                    draw_line(a.x, a.y, b.x, b.y, thickness, color);
+                }
+            }
+        };
+
+        for (k, b) in self.boundaries.iter().enumerate() {
+            // Low-density branch up to the critical point and back down the
+            // high-density branch; in the (T, µ) plane both coincide for the
+            // binodal.
+            let dome = |curve: &[(StatePoint, StatePoint)]| -> Vec<StatePoint> {
+                curve
+                    .iter()
+                    .map(|&(low, _)| low)
+                    .chain(b.critical_point)
+                    .chain(curve.iter().rev().map(|&(_, high)| high))
+                    .collect()
+            };
+            match self.axes {
+                PhaseAxes::TemperatureChemPotential => {
+                    let branch = |curve: &[(StatePoint, StatePoint)], high: bool| {
+                        curve
+                            .iter()
+                            .map(|&(low, hi)| if high { hi } else { low })
+                            .chain(b.critical_point)
+                            .collect::<Vec<_>>()
+                    };
+                    polyline(&branch(&b.spinodal, false), 1.0, b.color);
+                    polyline(&branch(&b.spinodal, true), 1.0, b.color);
+                }
+                PhaseAxes::DensityTemperature => polyline(&dome(&b.spinodal), 1.0, b.color),
+            }
+            polyline(&dome(&b.binodal), 2.5, b.color);
+            if let Some(c) = b.critical_point {
+                let (x, y) = self.point(
+                    c.temperature as f32,
+                    c.chem_potential as f32,
+                    c.density as f32,
+                );
+                if axes.contains(x, y) {
+                    let p = axes.to_screen(x, y);
+                    draw_circle(p.x, p.y, 4.0, b.color);
+                }
+            }
+
+            let r = axes.rect;
+            let y = r.y + 20.0 + 20.0 * k as f32;
+            draw_rectangle(r.x + 6.0, y - 15.0, 190.0, 20.0, color_u8!(10, 10, 10, 160));
+            draw_line(r.x + 10.0, y - 5.0, r.x + 30.0, y - 5.0, 2.5, b.color);
+            draw_text(b.name, r.x + 36.0, y, 18.0, b.color);
+        }
+    }
+
+    /// The recorded path, fading from old to new.
+    fn draw_trajectory(&self, axes: &Axes) {
+        let n = self.trajectory.len();
+        let points: Vec<(f32, f32)> = self
+            .trajectory
+            .iter()
+            .map(|&(t, mu, d)| self.point(t, mu, d))
+            .collect();
+        for (k, pair) in points.windows(2).enumerate() {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            if !axes.contains(x1, y1) || !axes.contains(x2, y2) {
+                continue;
+            }
+            let a = axes.to_screen(x1, y1);
+            let b = axes.to_screen(x2, y2);
+            let alpha = 0.15 + 0.85 * (k + 1) as f32 / n as f32;
+            draw_line(a.x, a.y, b.x, b.y, 1.5, Color::new(1.0, 0.3, 0.3, alpha));
+        }
+    }
+}
+
+/// Vertical scale from 0 (bottom) to `max` (top); `color` takes the
+/// fraction of `max`.
+fn draw_colour_bar(rect: Rect, label: &str, max: f32, color: fn(f32) -> Color) {
+    let slices = 50;
+    let h = rect.h / slices as f32;
+    for k in 0..slices {
+        let f = (k as f32 + 0.5) / slices as f32;
+        draw_rectangle(
+            rect.x,
+            rect.y + rect.h - (k + 1) as f32 * h,
+            rect.w,
+            h + 0.5,
+            color(f),
+        );
+    }
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, LIGHTGRAY);
+    let ticks = nice_ticks(0.0, max, 4);
+    let step = ticks.get(1).map_or(max, |&t| t - ticks[0]);
+    for &v in &ticks {
+        let y = rect.y + rect.h - v / max * rect.h;
+        draw_line(rect.x + rect.w, y, rect.x + rect.w + 4.0, y, 1.0, LIGHTGRAY);
+        draw_text(
+            &format_tick(v, step),
+            rect.x + rect.w + 6.0,
+            y + 5.0,
+            16.0,
+            LIGHTGRAY,
+        );
+    }
+    draw_text(label, rect.x, rect.y - 8.0, 20.0, WHITE);
+}
@@ -0,0 +1,132 @@
+use macroquad::prelude::*;
+
+/// Maps data coordinates into a screen rectangle, with `y` increasing upward.
+#[derive(Clone, Copy)]
+pub struct Axes {
+    pub rect: Rect,
+    pub x_range: (f32, f32),
+    pub y_range: (f32, f32),
+}
+
+impl Axes {
+    pub fn new(rect: Rect, x_range: (f32, f32), y_range: (f32, f32)) -> Self {
+        Self {
+            rect,
+            x_range,
+            y_range,
+        }
+    }
+
+    pub fn to_screen(self, x: f32, y: f32) -> Vec2 {
+        let fx = (x - self.x_range.0) / (self.x_range.1 - self.x_range.0);
+        let fy = (y - self.y_range.0) / (self.y_range.1 - self.y_range.0);
+        vec2(
+            self.rect.x + fx * self.rect.w,
+            self.rect.y + (1.0 - fy) * self.rect.h,
+        )
+    }
+
+    /// Inverse of `to_screen`.
+    pub fn to_data(self, p: Vec2) -> (f32, f32) {
+        let fx = (p.x - self.rect.x) / self.rect.w;
+        let fy = 1.0 - (p.y - self.rect.y) / self.rect.h;
+        (
+            self.x_range.0 + fx * (self.x_range.1 - self.x_range.0),
+            self.y_range.0 + fy * (self.y_range.1 - self.y_range.0),
+        )
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let within = |v: f32, (a, b): (f32, f32)| v >= a.min(b) && v <= a.max(b);
+        within(x, self.x_range) && within(y, self.y_range)
+    }
+
+    /// Frame, tick marks with values and axis labels around `rect`.
+    pub fn draw(&self, x_label: &str, y_label: &str) {
+        let r = self.rect;
+        draw_rectangle_lines(r.x, r.y, r.w, r.h, 1.0, LIGHTGRAY);
+        self.draw_x_axis(6, x_label);
+        self.draw_y_axis(6, y_label);
+    }
+
+    /// Roughly `target` ticks with values below `rect`, and `label` under them.
+    pub fn draw_x_axis(&self, target: usize, label: &str) {
+        let r = self.rect;
+        let ticks = nice_ticks(self.x_range.0, self.x_range.1, target);
+        let step = tick_step(&ticks);
+        for &x in &ticks {
+            let p = self.to_screen(x, self.y_range.0);
+            draw_line(p.x, r.y + r.h, p.x, r.y + r.h + 5.0, 1.0, LIGHTGRAY);
+            let text = format_tick(x, step);
+            let dims = measure_text(&text, None, 16, 1.0);
+            draw_text(
+                &text,
+                p.x - dims.width / 2.0,
+                r.y + r.h + 18.0,
+                16.0,
+                LIGHTGRAY,
+            );
+        }
+        let dims = measure_text(label, None, 20, 1.0);
+        draw_text(
+            label,
+            r.x + (r.w - dims.width) / 2.0,
+            r.y + r.h + 36.0,
+            20.0,
+            WHITE,
+        );
+    }
+
+    /// Roughly `target` ticks with values left of `rect`, and `label` above them.
+    pub fn draw_y_axis(&self, target: usize, label: &str) {
+        let r = self.rect;
+        let ticks = nice_ticks(self.y_range.0, self.y_range.1, target);
+        let step = tick_step(&ticks);
+        for &y in &ticks {
+            let p = self.to_screen(self.x_range.0, y);
+            draw_line(r.x - 5.0, p.y, r.x, p.y, 1.0, LIGHTGRAY);
+            let text = format_tick(y, step);
+            let dims = measure_text(&text, None, 16, 1.0);
+            draw_text(&text, r.x - 8.0 - dims.width, p.y + 5.0, 16.0, LIGHTGRAY);
+        }
+        draw_text(label, r.x - 46.0, r.y - 8.0, 20.0, WHITE);
+    }
+}
+
+/// Round tick values (`1`, `2` or `5` × 10ⁿ apart) covering `[min, max]` with
+/// roughly `target` ticks.
+pub fn nice_ticks(min: f32, max: f32, target: usize) -> Vec<f32> {
+    let (lo, hi) = (min.min(max), min.max(max));
+    let span = hi - lo;
+    if span <= 0.0 || !span.is_finite() {
+        return vec![lo];
+    }
+    let raw = span / target.max(1) as f32;
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&s| s >= raw)
+        .unwrap_or(10.0 * magnitude);
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    (first..=last).map(|k| k as f32 * step).collect()
+}
+
+fn tick_step(ticks: &[f32]) -> f32 {
+    match ticks {
+        [a, b, ..] => b - a,
+        _ => 1.0,
+    }
+}
+
+/// Formats `value` with as many decimals as `step` needs.
+pub fn format_tick(value: f32, step: f32) -> String {
+    let decimals = (-step.abs().log10().floor()).max(0.0) as usize;
+    let value = if value.abs() < step * 1e-3 {
+        0.0
+    } else {
+        value
+    };
+    format!("{value:.decimals$}")
+}
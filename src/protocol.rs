@@ -0,0 +1,228 @@
+use crate::core::{Lattice, Site};
+use crate::paint::{BrushShape, Stroke};
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// Parameters a protocol can change between sweeps.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Parameter {
+    Temperature,
+    ChemPotential,
+    Interaction,
+    Epsilon0,
+    Alpha,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 5] = [
+        Parameter::Temperature,
+        Parameter::ChemPotential,
+        Parameter::Interaction,
+        Parameter::Epsilon0,
+        Parameter::Alpha,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Parameter::Temperature => "temperature",
+            Parameter::ChemPotential => "chem_potential",
+            Parameter::Interaction => "interaction",
+            Parameter::Epsilon0 => "epsilon0",
+            Parameter::Alpha => "alpha",
+        }
+    }
+}
+
+/// One protocol line after its sweep number.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    /// A fresh lattice from `seed` with the couplings in effect.
+    Rebuild {
+        seed: u64,
+        width: usize,
+        height: usize,
+        init_density: f32,
+    },
+    Set(Parameter, f32),
+    Paint(Stroke),
+    /// Length of the run.
+    End,
+}
+
+impl Action {
+    /// Parses the words after the sweep number; `None` for an unknown action.
+    pub fn parse(words: &[&str]) -> Option<Self> {
+        let site = |word: &str| match word {
+            "molecule" => Some(Site::Molecule),
+            "empty" => Some(Site::Empty),
+            _ => None,
+        };
+        match words {
+            ["rebuild", seed, width, height, init_density] => Some(Action::Rebuild {
+                seed: seed.parse().ok()?,
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+                init_density: init_density.parse().ok()?,
+            }),
+            ["set", name, value] => {
+                let parameter = Parameter::ALL.into_iter().find(|p| p.name() == *name)?;
+                Some(Action::Set(parameter, value.parse().ok()?))
+            }
+            ["paint", s, shape, radius, x0, y0, x1, y1] => Some(Action::Paint(Stroke {
+                site: site(s)?,
+                shape: BrushShape::from_name(shape)?,
+                radius: radius.parse().ok()?,
+                from: (x0.parse().ok()?, y0.parse().ok()?),
+                to: (x1.parse().ok()?, y1.parse().ok()?),
+            })),
+            ["end"] => Some(Action::End),
+            _ => None,
+        }
+    }
+
+    /// Rebuilds replace `lattice` with a new one; strokes are painted on it.
+    /// Parameter changes are left to the caller.
+    pub fn apply_to_lattice(&self, lattice: &mut Lattice) {
+        match *self {
+            Action::Rebuild {
+                seed,
+                width,
+                height,
+                init_density,
+            } => {
+                *lattice = Lattice::new_seeded(
+                    width,
+                    height,
+                    lattice.j,
+                    lattice.epsilon0,
+                    lattice.alpha,
+                    init_density,
+                    seed,
+                );
+            }
+            Action::Paint(stroke) => stroke.apply(lattice),
+            Action::Set(..) | Action::End => {}
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Rebuild {
+                seed,
+                width,
+                height,
+                init_density,
+            } => write!(f, "rebuild {seed} {width} {height} {init_density}"),
+            Action::Set(parameter, value) => write!(f, "set {} {value}", parameter.name()),
+            Action::Paint(s) => {
+                let site = match s.site {
+                    Site::Molecule => "molecule",
+                    Site::Empty => "empty",
+                };
+                write!(
+                    f,
+                    "paint {site} {} {} {} {} {} {}",
+                    s.shape.name(),
+                    s.radius,
+                    s.from.0,
+                    s.from.1,
+                    s.to.0,
+                    s.to.1
+                )
+            }
+            Action::End => write!(f, "end"),
+        }
+    }
+}
+
+fn invalid_line(number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {number}: {message}"),
+    )
+}
+
+/// Reads `sweep words…` lines, skipping blank and `#` comment lines, with
+/// sweeps in non-decreasing order; `parse` turns the words after the sweep
+/// into an entry, e.g. `Action::parse`. An entry at sweep `s` takes effect
+/// after `s` sweeps.
+pub fn read<T>(
+    input: impl BufRead,
+    parse: impl Fn(&[&str]) -> Option<T>,
+) -> io::Result<Vec<(u64, T)>> {
+    let mut entries: Vec<(u64, T)> = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let sweep: u64 = words
+            .next()
+            .and_then(|w| w.parse().ok())
+            .ok_or_else(|| invalid_line(i + 1, "expected a sweep number"))?;
+        let words: Vec<&str> = words.collect();
+        let entry =
+            parse(&words).ok_or_else(|| invalid_line(i + 1, "unknown or malformed entry"))?;
+        if entries.last().is_some_and(|&(last, _)| sweep < last) {
+            return Err(invalid_line(i + 1, "sweep numbers must not decrease"));
+        }
+        entries.push((sweep, entry));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_round_trip_through_text() {
+        let mut actions = vec![
+            Action::Rebuild {
+                seed: u64::MAX,
+                width: 200,
+                height: 150,
+                init_density: 0.1 + 0.2,
+            },
+            Action::End,
+        ];
+        for (i, parameter) in Parameter::ALL.into_iter().enumerate() {
+            actions.push(Action::Set(parameter, -1.0 / (i + 3) as f32));
+        }
+        for shape in BrushShape::ALL {
+            for site in [Site::Molecule, Site::Empty] {
+                actions.push(Action::Paint(Stroke {
+                    site,
+                    shape,
+                    radius: 3,
+                    from: (-2, 7),
+                    to: (205, -1),
+                }));
+            }
+        }
+        for action in actions {
+            let text = action.to_string();
+            let words: Vec<&str> = text.split_whitespace().collect();
+            assert!(Action::parse(&words) == Some(action), "{text}");
+        }
+    }
+
+    #[test]
+    fn read_skips_comments_and_rejects_decreasing_sweeps() {
+        let text = "# header\n\n0 set temperature 0.5\n10 end\n";
+        let entries = read(text.as_bytes(), Action::parse).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[1] == (10, Action::End));
+
+        let text = "5 set temperature 0.5\n3 end\n";
+        let error = read(text.as_bytes(), Action::parse).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2:"));
+
+        assert!(read("0 jump 3\n".as_bytes(), Action::parse).is_err());
+    }
+}
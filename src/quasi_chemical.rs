@@ -0,0 +1,203 @@
+use crate::mean_field::{StatePoint, Theory, Z, common_tangent, logistic, logit};
+
+/// Bethe–Peierls / quasi-chemical approximation to the same Hamiltonian as
+/// `MeanField`. Nearest-neighbour pairs are counted exactly on a tree of
+/// coordination `z`, with the pair fractions fixed by the quasi-chemical
+/// condition `p₁₁ p₀₀ / p₁₀² = e^{βJ}`. The `αN²/V` term has infinite range
+/// and enters as in mean field.
+#[derive(Clone, Copy, PartialEq)]
+pub struct QuasiChemical {
+    pub j: f32,
+    pub epsilon0: f32,
+    pub alpha: f32,
+}
+
+/// Logarithms of the site and pair fractions at one density.
+struct Pairs {
+    d: f64,
+    ln_d: f64,
+    ln_1md: f64,
+    /// Fraction of occupied–occupied bonds.
+    p11: f64,
+    ln_p11: f64,
+    /// Fraction of occupied–empty bonds (each orientation).
+    ln_p10: f64,
+    ln_p00: f64,
+}
+
+impl QuasiChemical {
+    /// Pair fractions at `ρ = 1 / (1 + e^−u)`, arranged to avoid the
+    /// cancellation in `p₁₁ = ρ − p₁₀` at extreme densities.
+    fn pairs(&self, u: f64, temp: f64) -> Pairs {
+        let (d, ln_d, ln_1md) = logistic(u);
+        let q = ln_1md.exp();
+        let boltzmann_m1 = (self.j as f64 / temp).exp_m1();
+        let s = (1.0 + 4.0 * d * q * boltzmann_m1).sqrt();
+        let ln_norm = (1.0 + s).ln();
+        let ln_p11 = 2.0 * ln_d + (2.0 + 4.0 * q * boltzmann_m1 / (1.0 + s)).ln() - ln_norm;
+        Pairs {
+            d,
+            ln_d,
+            ln_1md,
+            p11: ln_p11.exp(),
+            ln_p11,
+            ln_p10: 2f64.ln() + ln_d + ln_1md - ln_norm,
+            ln_p00: 2.0 * ln_1md + (2.0 + 4.0 * d * boltzmann_m1 / (1.0 + s)).ln() - ln_norm,
+        }
+    }
+
+    /// `µ(ρ) = φ'(ρ)`; the pair fractions are stationary, so only their
+    /// explicit dependence on `ρ` contributes.
+    fn chem_potential(&self, u: f64, temp: f64) -> f64 {
+        let z = Z as f64;
+        let p = self.pairs(u, temp);
+        temp * (z / 2.0 * (p.ln_p11 - p.ln_p00) - (z - 1.0) * u) - z / 2.0 * self.j as f64
+            + self.epsilon0 as f64
+            - 2.0 * self.alpha as f64 * p.d
+    }
+
+    /// `φ − µρ` with `φ = −J (z/2) p₁₁ + ε0 ρ − αρ² − T s`, where the Bethe
+    /// entropy is `s = −(z/2) Σ p ln p + (z − 1) Σ ρ ln ρ` over pairs and sites.
+    fn grand_potential(&self, u: f64, temp: f64, mu: f64) -> f64 {
+        let z = Z as f64;
+        let p = self.pairs(u, temp);
+        let plnp = |ln: f64| ln.exp() * ln;
+        let pair_term = plnp(p.ln_p11) + 2.0 * plnp(p.ln_p10) + plnp(p.ln_p00);
+        let site_term = plnp(p.ln_d) + plnp(p.ln_1md);
+        -(self.j as f64) * z / 2.0 * p.p11 + (self.epsilon0 as f64 - mu) * p.d
+            - self.alpha as f64 * p.d * p.d
+            + temp * (z / 2.0 * pair_term - (z - 1.0) * site_term)
+    }
+
+    /// Quasi-chemical counterpart of `MeanField::calculate_ftc`: `ln P(ρ)` per
+    /// site, `−(φ − µρ) / T`.
+    pub fn calculate_ftc(&self, d: f32, temp: f32, chem_potential: f32) -> f32 {
+        if d <= 0.0 || d >= 1.0 || temp <= 0.0 {
+            return -f32::INFINITY;
+        }
+        let (temp, mu) = (temp as f64, chem_potential as f64);
+        (-self.grand_potential(logit(d as f64), temp, mu) / temp) as f32
+    }
+
+    fn slope_at_half(&self, temp: f64) -> f64 {
+        let h = 1e-4;
+        (self.chem_potential(h, temp) - self.chem_potential(-h, temp)) / (2.0 * h)
+    }
+
+    /// Spinodals in `u = ln(ρ / (1 − ρ))`; at low T the gas spinodal lies near
+    /// `ρ ≈ e^{−J/T} / z`, below what `ρ` itself resolves next to 1.
+    fn spinodal_logits(&self, temp: f64) -> Option<(f64, f64)> {
+        let t_c = self.critical_point()?.temperature;
+        if temp <= 0.0 || temp >= t_c {
+            return None;
+        }
+        Some((
+            self.extremum(temp, -1e3, 0.0, true),
+            self.extremum(temp, 0.0, 1e3, false),
+        ))
+    }
+
+    /// Stationary point of `µ(u)` by ternary search on `(lo, hi)`.
+    fn extremum(&self, temp: f64, mut lo: f64, mut hi: f64, maximum: bool) -> f64 {
+        let sign = if maximum { 1.0 } else { -1.0 };
+        for _ in 0..200 {
+            let a = lo + (hi - lo) / 3.0;
+            let b = hi - (hi - lo) / 3.0;
+            if sign * self.chem_potential(a, temp) < sign * self.chem_potential(b, temp) {
+                lo = a;
+            } else {
+                hi = b;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+}
+
+impl Theory for QuasiChemical {
+    fn name(&self) -> &'static str {
+        "quasi-chemical"
+    }
+
+    /// `T_c` where `φ''(1/2)` vanishes, found by bisection below the mean-field
+    /// value `(zJ/2 + α) / 2`. For `α = 0`, `T_c = J / (2 ln(z / (z − 2)))`.
+    fn critical_point(&self) -> Option<StatePoint> {
+        let t_mf = (Z * self.j / 2.0 + self.alpha) as f64 / 2.0;
+        if t_mf <= 0.0 {
+            return None;
+        }
+        let (mut lo, mut hi) = (1e-3 * t_mf, 2.0 * t_mf);
+        if self.slope_at_half(lo) >= 0.0 || self.slope_at_half(hi) <= 0.0 {
+            return None;
+        }
+        for _ in 0..100 {
+            let mid = 0.5 * (lo + hi);
+            if self.slope_at_half(mid) < 0.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let t_c = 0.5 * (lo + hi);
+        Some(StatePoint {
+            temperature: t_c,
+            chem_potential: self.chem_potential(0.0, t_c),
+            density: 0.5,
+        })
+    }
+
+    fn spinodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)> {
+        let (u_low, u_high) = self.spinodal_logits(temp)?;
+        let state = |u: f64| StatePoint {
+            temperature: temp,
+            chem_potential: self.chem_potential(u, temp),
+            density: logistic(u).0,
+        };
+        Some((state(u_low), state(u_high)))
+    }
+
+    fn binodal(&self, temp: f64) -> Option<(StatePoint, StatePoint)> {
+        Some(common_tangent(
+            temp,
+            self.spinodal_logits(temp)?,
+            |u| self.chem_potential(u, temp),
+            |u, mu| self.grand_potential(u, temp, mu),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critical_temperature_is_j_over_two_ln_two() {
+        let model = QuasiChemical {
+            j: 1.3,
+            epsilon0: 0.4,
+            alpha: 0.0,
+        };
+        let t_c = model.critical_point().unwrap().temperature;
+        assert!((t_c - 1.3 / (2.0 * 2f64.ln())).abs() < 1e-5, "{t_c}");
+    }
+
+    #[test]
+    fn binodal_sits_at_the_symmetric_chem_potential_inside_the_spinodal() {
+        let model = QuasiChemical {
+            j: 1.0,
+            epsilon0: 0.3,
+            alpha: 0.2,
+        };
+        let t_c = model.critical_point().unwrap().temperature;
+        let mu = (model.epsilon0 - Z * model.j / 2.0 - model.alpha) as f64;
+        for fraction in [0.3, 0.6, 0.9, 0.99] {
+            let temp = fraction * t_c;
+            let (low, high) = model.binodal(temp).unwrap();
+            let (s_low, s_high) = model.spinodal(temp).unwrap();
+            assert!((low.chem_potential - mu).abs() < 1e-6, "T/Tc = {fraction}");
+            assert!((low.density + high.density - 1.0).abs() < 1e-6);
+            assert!(low.density < s_low.density && s_high.density < high.density);
+            assert!(s_low.density < s_high.density);
+        }
+        assert!(model.binodal(1.01 * t_c).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::core::Lattice;
+use ::rand::random;
+
+/// Replica-exchange (parallel tempering) driver over a ladder of (T, µ) points.
+///
+/// `lattices[k]` is always the configuration currently sitting at `ladder[k]`;
+/// an accepted swap exchanges configurations between neighbouring rungs.
+pub struct ReplicaExchange {
+    pub ladder: Vec<(f32, f32)>,
+    pub lattices: Vec<Lattice>,
+    attempts: Vec<u64>,
+    accepted: Vec<u64>,
+    swap_rounds: u64,
+}
+
+impl ReplicaExchange {
+    pub fn new(ladder: Vec<(f32, f32)>, mut make_lattice: impl FnMut() -> Lattice) -> Self {
+        let lattices = ladder.iter().map(|_| make_lattice()).collect();
+        let pairs = ladder.len().saturating_sub(1);
+        Self {
+            ladder,
+            lattices,
+            attempts: vec![0; pairs],
+            accepted: vec![0; pairs],
+            swap_rounds: 0,
+        }
+    }
+
+    /// One sweep of every replica at its own rung, run on one thread per replica.
+    pub fn sweep(&mut self) {
+        std::thread::scope(|scope| {
+            for (lattice, &(temp, mu)) in self.lattices.iter_mut().zip(&self.ladder) {
+                scope.spawn(move || lattice.step(temp, mu));
+            }
+        });
+    }
+
+    /// Attempts swaps between neighbouring rungs, alternating between even and
+    /// odd pairs on successive calls. A swap of configurations `a` and `b`
+    /// between rungs `i` and `j` is accepted with probability
+    /// `min(1, exp[(βi − βj)(Ea − Eb) − (βi µi − βj µj)(Na − Nb)])`.
+    pub fn attempt_swaps(&mut self) {
+        let start = (self.swap_rounds % 2) as usize;
+        self.swap_rounds += 1;
+        for i in (start..self.attempts.len()).step_by(2) {
+            let (t_i, mu_i) = self.ladder[i];
+            let (t_j, mu_j) = self.ladder[i + 1];
+            let (beta_i, beta_j) = (1.0 / t_i as f64, 1.0 / t_j as f64);
+            let (e_i, e_j) = (self.lattices[i].energy(), self.lattices[i + 1].energy());
+            let n_i = self.lattices[i].molecule_count() as f64;
+            let n_j = self.lattices[i + 1].molecule_count() as f64;
+            let log_ratio = (beta_i - beta_j) * (e_i - e_j)
+                - (beta_i * mu_i as f64 - beta_j * mu_j as f64) * (n_i - n_j);
+
+            self.attempts[i] += 1;
+            if log_ratio >= 0.0 || random::<f64>() < log_ratio.exp() {
+                self.lattices.swap(i, i + 1);
+                self.accepted[i] += 1;
+            }
+        }
+    }
+
+    /// Fraction of accepted swaps between rungs `k` and `k + 1`.
+    pub fn acceptance_rates(&self) -> Vec<f64> {
+        self.attempts
+            .iter()
+            .zip(&self.accepted)
+            .map(|(&a, &n)| if a > 0 { n as f64 / a as f64 } else { 0.0 })
+            .collect()
+    }
+}
@@ -0,0 +1,388 @@
+use crate::core::{Lattice, model_energy};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::Path;
+
+/// Joint histogram of occupied bonds and molecule count sampled at one (T, µ).
+///
+/// Together with the model parameters, `(bonds, molecules)` fixes the energy
+/// exactly, so the histogram carries everything needed to reweight the run.
+#[derive(Clone)]
+pub struct Histogram {
+    pub temperature: f32,
+    pub chem_potential: f32,
+    pub j: f32,
+    pub epsilon0: f32,
+    pub alpha: f32,
+    pub sites: usize,
+    counts: HashMap<(usize, usize), u64>,
+    samples: u64,
+}
+
+impl Histogram {
+    pub fn new(lattice: &Lattice, temperature: f32, chem_potential: f32) -> Self {
+        Self {
+            temperature,
+            chem_potential,
+            j: lattice.j,
+            epsilon0: lattice.epsilon0,
+            alpha: lattice.alpha,
+            sites: lattice.width * lattice.height,
+            counts: HashMap::new(),
+            samples: 0,
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn record(&mut self, lattice: &Lattice) {
+        let key = (lattice.bond_count(), lattice.molecule_count());
+        *self.counts.entry(key).or_default() += 1;
+        self.samples += 1;
+    }
+
+    fn energy(&self, bonds: usize, molecules: usize) -> f64 {
+        model_energy(
+            self.j,
+            self.epsilon0,
+            self.alpha,
+            self.sites,
+            bonds,
+            molecules,
+        )
+    }
+
+    fn same_model(&self, other: &Histogram) -> bool {
+        self.j == other.j
+            && self.epsilon0 == other.epsilon0
+            && self.alpha == other.alpha
+            && self.sites == other.sites
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "# temperature={},chem_potential={},interaction={},epsilon0={},alpha={},sites={}",
+            self.temperature, self.chem_potential, self.j, self.epsilon0, self.alpha, self.sites
+        )?;
+        writeln!(file, "bonds,molecules,count")?;
+        let mut keys: Vec<_> = self.counts.keys().copied().collect();
+        keys.sort_unstable();
+        for key in keys {
+            writeln!(file, "{},{},{}", key.0, key.1, self.counts[&key])?;
+        }
+        Ok(())
+    }
+
+    pub fn load_csv(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let invalid =
+            |msg: String| Error::new(ErrorKind::InvalidData, format!("{}: {msg}", path.display()));
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let meta = lines.next().transpose()?.unwrap_or_default();
+        let meta = meta
+            .strip_prefix("# ")
+            .ok_or_else(|| invalid("missing parameter line".into()))?;
+        let mut params = HashMap::new();
+        for pair in meta.split(',') {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("malformed parameter `{pair}`")))?;
+            let value: f32 = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("malformed value for `{key}`")))?;
+            params.insert(key.trim().to_string(), value);
+        }
+        let param = |key: &str| {
+            params
+                .get(key)
+                .copied()
+                .ok_or_else(|| invalid(format!("missing parameter `{key}`")))
+        };
+
+        let mut histogram = Self {
+            temperature: param("temperature")?,
+            chem_potential: param("chem_potential")?,
+            j: param("interaction")?,
+            epsilon0: param("epsilon0")?,
+            alpha: param("alpha")?,
+            sites: param("sites")? as usize,
+            counts: HashMap::new(),
+            samples: 0,
+        };
+
+        lines.next().transpose()?;
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<u64> = line
+                .split(',')
+                .map(|f| f.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(format!("malformed row `{line}`")))?;
+            let [bonds, molecules, count] = fields[..] else {
+                return Err(invalid(format!("malformed row `{line}`")));
+            };
+            *histogram
+                .counts
+                .entry((bonds as usize, molecules as usize))
+                .or_default() += count;
+            histogram.samples += count;
+        }
+        Ok(histogram)
+    }
+}
+
+/// Thermodynamic averages per site at one (T, µ).
+#[derive(Clone, Copy)]
+pub struct Observables {
+    pub density: f64,
+    pub energy: f64,
+    /// `(⟨H²⟩ − ⟨H⟩²) / (T² N)` with `H = E − µN`; `E` alone would pick up
+    /// the jumps in `N` between coexisting phases.
+    pub heat_capacity: f64,
+    /// `∂⟨ρ⟩/∂µ = (⟨N²⟩ − ⟨N⟩²) / (T N)`.
+    pub susceptibility: f64,
+}
+
+fn log_sum_exp(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = values.clone().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        return max;
+    }
+    max + values.map(|v| (v - max).exp()).sum::<f64>().ln()
+}
+
+/// Density of states `g(E, N)` over all visited macrostates, estimated from
+/// one or more histograms with the Ferrenberg–Swendsen multi-histogram (WHAM)
+/// equations. A single histogram reduces to plain single-histogram reweighting.
+pub struct Reweighting {
+    sites: usize,
+    /// `(E, N, ln g)` for every macrostate seen in any histogram.
+    states: Vec<(f64, f64, f64)>,
+    iterations: usize,
+}
+
+impl Reweighting {
+    pub fn new(
+        histograms: &[Histogram],
+        tolerance: f64,
+        max_iterations: usize,
+    ) -> std::io::Result<Self> {
+        let Some(first) = histograms.first() else {
+            return Err(Error::new(ErrorKind::InvalidInput, "no histograms given"));
+        };
+        if let Some(h) = histograms.iter().find(|h| !first.same_model(h)) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "histogram at T={}, µ={} was recorded with different J, ε0, α or lattice size",
+                    h.temperature, h.chem_potential
+                ),
+            ));
+        }
+
+        let mut totals: HashMap<(usize, usize), u64> = HashMap::new();
+        for h in histograms {
+            for (&key, &count) in &h.counts {
+                *totals.entry(key).or_default() += count;
+            }
+        }
+        let mut keys: Vec<_> = totals.keys().copied().collect();
+        keys.sort_unstable();
+        let energies: Vec<(f64, f64)> = keys
+            .iter()
+            .map(|&(b, n)| (first.energy(b, n), n as f64))
+            .collect();
+        let ln_counts: Vec<f64> = keys.iter().map(|k| (totals[k] as f64).ln()).collect();
+
+        let runs: Vec<(f64, f64, f64)> = histograms
+            .iter()
+            .map(|h| {
+                (
+                    1.0 / h.temperature as f64,
+                    h.chem_potential as f64,
+                    (h.samples as f64).ln(),
+                )
+            })
+            .collect();
+        let mut ln_z = vec![0.0; runs.len()];
+        let mut ln_g = vec![0.0; keys.len()];
+        let mut iterations = 0;
+        while iterations < max_iterations {
+            iterations += 1;
+            for (s, &(e, n)) in energies.iter().enumerate() {
+                let denom = log_sum_exp(
+                    runs.iter()
+                        .zip(&ln_z)
+                        .map(|(&(beta, mu, ln_n), &f)| ln_n - beta * (e - mu * n) - f),
+                );
+                ln_g[s] = ln_counts[s] - denom;
+            }
+            let mut next: Vec<f64> = runs
+                .iter()
+                .map(|&(beta, mu, _)| {
+                    log_sum_exp(
+                        energies
+                            .iter()
+                            .zip(&ln_g)
+                            .map(|(&(e, n), &g)| g - beta * (e - mu * n)),
+                    )
+                })
+                .collect();
+            let shift = next[0];
+            for f in next.iter_mut() {
+                *f -= shift;
+            }
+            let change = next
+                .iter()
+                .zip(&ln_z)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max);
+            ln_z = next;
+            if change < tolerance {
+                break;
+            }
+        }
+
+        let states = energies
+            .into_iter()
+            .zip(ln_g)
+            .map(|((e, n), g)| (e, n, g))
+            .collect();
+        Ok(Self {
+            sites: first.sites,
+            states,
+            iterations,
+        })
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn observables(&self, temperature: f64, chem_potential: f64) -> Observables {
+        let beta = 1.0 / temperature;
+        let log_weights: Vec<f64> = self
+            .states
+            .iter()
+            .map(|&(e, n, g)| g - beta * (e - chem_potential * n))
+            .collect();
+        let max = log_weights
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = log_weights.iter().map(|w| (w - max).exp()).collect();
+        let z: f64 = weights.iter().sum();
+
+        let mean = |f: &dyn Fn(f64, f64) -> f64| {
+            self.states
+                .iter()
+                .zip(&weights)
+                .map(|(&(e, n, _), w)| w * f(e, n))
+                .sum::<f64>()
+                / z
+        };
+        let mean_e = mean(&|e, _| e);
+        let mean_n = mean(&|_, n| n);
+        let mean_h = mean_e - chem_potential * mean_n;
+        let var_h = mean(&|e, n| (e - chem_potential * n - mean_h).powi(2));
+        let var_n = mean(&|_, n| (n - mean_n).powi(2));
+        let v = self.sites as f64;
+        Observables {
+            density: mean_n / v,
+            energy: mean_e / v,
+            heat_capacity: beta * beta * var_h / v,
+            susceptibility: beta * var_n / v,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Site;
+
+    /// `(bonds, molecules)` of every configuration of a 3×3 lattice.
+    fn macrostates(lattice: &mut Lattice) -> Vec<(usize, usize)> {
+        (0..1u32 << 9)
+            .map(|mask| {
+                for i in 0..9 {
+                    lattice.grid[i % 3][i / 3] = if mask >> i & 1 == 1 {
+                        Site::Molecule
+                    } else {
+                        Site::Empty
+                    };
+                }
+                (lattice.bond_count(), mask.count_ones() as usize)
+            })
+            .collect()
+    }
+
+    /// Grand-canonical weights of `states` at (T, µ), normalised to one.
+    fn probabilities(h: &Histogram, states: &[(usize, usize)], t: f64, mu: f64) -> Vec<f64> {
+        let weights: Vec<f64> = states
+            .iter()
+            .map(|&(b, n)| (-(h.energy(b, n) - mu * n as f64) / t).exp())
+            .collect();
+        let z: f64 = weights.iter().sum();
+        weights.iter().map(|w| w / z).collect()
+    }
+
+    /// A histogram holding the exact distribution at (T, µ), scaled to
+    /// integer counts.
+    fn exact_histogram(lattice: &mut Lattice, t: f32, mu: f32) -> Histogram {
+        let mut h = Histogram::new(lattice, t, mu);
+        let states = macrostates(lattice);
+        for (&key, p) in states
+            .iter()
+            .zip(probabilities(&h, &states, t as f64, mu as f64))
+        {
+            let count = (p * 1e12).round() as u64;
+            *h.counts.entry(key).or_default() += count;
+            h.samples += count;
+        }
+        h
+    }
+
+    #[test]
+    fn two_temperatures_reweight_to_exact_mean_count_between_them() {
+        let mut lattice = Lattice::new_seeded(3, 3, 1.0, 0.3, 0.5, 0.0, 1);
+        let mu = -1.0;
+        let histograms = [
+            exact_histogram(&mut lattice, 0.8, mu),
+            exact_histogram(&mut lattice, 2.0, mu),
+        ];
+        let reweighting = Reweighting::new(&histograms, 1e-12, 10_000).unwrap();
+
+        let t = 1.2;
+        let states = macrostates(&mut lattice);
+        let exact: f64 = probabilities(&histograms[0], &states, t, mu as f64)
+            .iter()
+            .zip(&states)
+            .map(|(p, &(_, n))| p * n as f64)
+            .sum();
+        let obs = reweighting.observables(t, mu as f64);
+        assert!(
+            (obs.density * 9.0 - exact).abs() < 1e-6,
+            "{} vs {exact}",
+            obs.density * 9.0
+        );
+        // The relative free energy of the two runs had to be iterated.
+        assert!(reweighting.iterations() > 1);
+    }
+}
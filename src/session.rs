@@ -0,0 +1,314 @@
+use crate::control_panel::Settings;
+use crate::core::Lattice;
+use crate::protocol::{self, Action, Parameter};
+use macroquad::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Keys that only change what is shown; sessions record them so a replay
+/// shows the same panels.
+pub const VIEW_KEYS: [KeyCode; 11] = [
+    KeyCode::M,
+    KeyCode::D,
+    KeyCode::L,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::A,
+    KeyCode::G,
+];
+
+/// A session line: a protocol action, or a view key that `ps_cli` ignores.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Entry {
+    Action(Action),
+    Key(KeyCode),
+}
+
+impl Entry {
+    fn parse(words: &[&str]) -> Option<Self> {
+        match words {
+            ["key", name] => VIEW_KEYS
+                .into_iter()
+                .find(|key| format!("{key:?}") == *name)
+                .map(Entry::Key),
+            _ => Action::parse(words).map(Entry::Action),
+        }
+    }
+}
+
+fn parameters(settings: &Settings) -> [(Parameter, f32); 5] {
+    [
+        (Parameter::Temperature, settings.temperature),
+        (Parameter::ChemPotential, settings.chem_potential),
+        (Parameter::Interaction, settings.j),
+        (Parameter::Epsilon0, settings.epsilon0),
+        (Parameter::Alpha, settings.alpha),
+    ]
+}
+
+/// Records a session as sweep-numbered entries, counted from its start.
+/// A session must begin with a rebuild so that it can be replayed from the
+/// seed. Parameter changes are found by comparing the settings with those
+/// last recorded.
+pub struct Recorder {
+    start: u64,
+    recorded: Settings,
+    entries: Vec<(u64, Entry)>,
+}
+
+impl Recorder {
+    /// Starts at global sweep `step` with the current parameters.
+    pub fn new(settings: &Settings, step: u64) -> Self {
+        let entries = parameters(settings)
+            .into_iter()
+            .map(|(p, v)| (0, Entry::Action(Action::Set(p, v))))
+            .collect();
+        Self {
+            start: step,
+            recorded: *settings,
+            entries,
+        }
+    }
+
+    fn push(&mut self, step: u64, entry: Entry) {
+        self.entries.push((step - self.start, entry));
+    }
+
+    /// Records the parameters that changed since the last call.
+    pub fn sync(&mut self, settings: &Settings, step: u64) {
+        let old = parameters(&self.recorded);
+        for ((parameter, value), (_, before)) in parameters(settings).into_iter().zip(old) {
+            if value != before {
+                self.push(step, Entry::Action(Action::Set(parameter, value)));
+            }
+        }
+        self.recorded = *settings;
+    }
+
+    pub fn action(&mut self, settings: &Settings, step: u64, action: Action) {
+        self.sync(settings, step);
+        self.push(step, Entry::Action(action));
+    }
+
+    pub fn key(&mut self, step: u64, key: KeyCode) {
+        self.push(step, Entry::Key(key));
+    }
+
+    /// Sweeps recorded so far.
+    pub fn sweeps(&self, step: u64) -> u64 {
+        step - self.start
+    }
+
+    /// Writes `session_{step}_steps.txt` with every entry and
+    /// `session_{step}_steps.protocol` without the view keys, for
+    /// `ps_cli --protocol`. Returns the session path.
+    pub fn finish(mut self, settings: &Settings, dir: &Path, step: u64) -> io::Result<PathBuf> {
+        self.sync(settings, step);
+        self.push(step, Entry::Action(Action::End));
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+        let session = dir.join(format!("session_{step}_steps.txt"));
+        let mut out = BufWriter::new(File::create(&session)?);
+        writeln!(out, "# lattice_simulator session")?;
+        for (sweep, entry) in &self.entries {
+            match entry {
+                Entry::Action(action) => writeln!(out, "{sweep} {action}")?,
+                Entry::Key(key) => writeln!(out, "{sweep} key {key:?}")?,
+            }
+        }
+        out.flush()?;
+
+        let mut out = BufWriter::new(File::create(session.with_extension("protocol"))?);
+        writeln!(
+            out,
+            "# ps_cli protocol, run with: ps_cli --protocol <this file>"
+        )?;
+        for (sweep, entry) in &self.entries {
+            if let Entry::Action(action) = entry {
+                writeln!(out, "{sweep} {action}")?;
+            }
+        }
+        out.flush()?;
+        Ok(session)
+    }
+}
+
+/// A fresh lattice with the size and initial density of `settings`.
+pub fn rebuild(settings: &Settings, seed: u64) -> Action {
+    Action::Rebuild {
+        seed,
+        width: settings.width,
+        height: settings.height,
+        init_density: settings.init_density,
+    }
+}
+
+/// Applies a recorded or live action to the settings and the lattice.
+pub fn apply(action: Action, settings: &mut Settings, lattice: &mut Lattice) {
+    match action {
+        Action::Set(Parameter::Temperature, value) => settings.temperature = value,
+        Action::Set(Parameter::ChemPotential, value) => settings.chem_potential = value,
+        Action::Set(Parameter::Interaction, value) => settings.j = value,
+        Action::Set(Parameter::Epsilon0, value) => settings.epsilon0 = value,
+        Action::Set(Parameter::Alpha, value) => settings.alpha = value,
+        Action::Rebuild {
+            width,
+            height,
+            init_density,
+            ..
+        } => {
+            settings.width = width;
+            settings.height = height;
+            settings.init_density = init_density;
+        }
+        Action::Paint(_) | Action::End => {}
+    }
+    lattice.j = settings.j;
+    lattice.epsilon0 = settings.epsilon0;
+    lattice.alpha = settings.alpha;
+    action.apply_to_lattice(lattice);
+}
+
+/// A recorded session played back from global sweep `start`.
+pub struct Replay {
+    start: u64,
+    entries: Vec<(u64, Entry)>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn load(path: &Path, start: u64) -> io::Result<Self> {
+        let entries = protocol::read(BufReader::new(File::open(path)?), Entry::parse)?;
+        let starts_fresh = entries
+            .iter()
+            .take_while(|&&(sweep, _)| sweep == 0)
+            .any(|(_, entry)| matches!(entry, Entry::Action(Action::Rebuild { .. })));
+        if !starts_fresh {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "a session must start with a rebuild at sweep 0",
+            ));
+        }
+        Ok(Self {
+            start,
+            entries,
+            next: 0,
+        })
+    }
+
+    /// The next entry due after `step` global sweeps, if any.
+    pub fn next_due(&mut self, step: u64) -> Option<Entry> {
+        let &(sweep, entry) = self.entries.get(self.next)?;
+        if self.start + sweep > step {
+            return None;
+        }
+        self.next += 1;
+        Some(entry)
+    }
+
+    /// Sweeps replayed so far and the length of the session.
+    pub fn progress(&self, step: u64) -> (u64, u64) {
+        let length = self.entries.last().map_or(0, |&(sweep, _)| sweep);
+        (step - self.start, length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Site;
+    use crate::paint::{BrushShape, Stroke};
+
+    fn settings(temperature: f32) -> Settings {
+        Settings {
+            temperature,
+            chem_potential: -1.0,
+            j: 0.5,
+            epsilon0: 0.0,
+            alpha: 0.0,
+            width: 24,
+            height: 16,
+            init_density: 0.5,
+            sweeps_per_frame: 1.0,
+            temperature_step: 0.01,
+            chem_potential_step: 0.02,
+            paused: false,
+        }
+    }
+
+    fn lattice(settings: &Settings, seed: u64) -> Lattice {
+        Lattice::new_seeded(
+            settings.width,
+            settings.height,
+            settings.j,
+            settings.epsilon0,
+            settings.alpha,
+            settings.init_density,
+            seed,
+        )
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_lattice() {
+        let dir = std::env::temp_dir().join(format!("session_test_{}", std::process::id()));
+
+        // Record from global sweep 40 on a lattice that is then rebuilt.
+        let mut live = settings(0.7);
+        let mut lattice_a = lattice(&live, 1);
+        let mut recorder = Recorder::new(&live, 40);
+        let start = rebuild(&live, 99);
+        recorder.action(&live, 40, start);
+        apply(start, &mut live, &mut lattice_a);
+        let stroke = Action::Paint(Stroke {
+            site: Site::Molecule,
+            shape: BrushShape::Disk,
+            radius: 2,
+            from: (3, 3),
+            to: (20, 9),
+        });
+        for step in 40..340 {
+            match step {
+                100 => live.temperature = 0.55,
+                150 => live.chem_potential = -0.8,
+                200 => {
+                    recorder.action(&live, step, stroke);
+                    apply(stroke, &mut live, &mut lattice_a);
+                }
+                260 => live.j = 0.6,
+                _ => {}
+            }
+            recorder.sync(&live, step);
+            lattice_a.j = live.j;
+            lattice_a.step(live.temperature, live.chem_potential);
+        }
+        let path = recorder.finish(&live, &dir, 340).unwrap();
+
+        // Replay from global sweep 7 with different starting settings.
+        let mut replayed = settings(1.2);
+        let mut lattice_b = lattice(&replayed, 2);
+        let mut replay = Replay::load(&path, 7).unwrap();
+        let mut step = 7;
+        'replay: loop {
+            while let Some(entry) = replay.next_due(step) {
+                match entry {
+                    Entry::Action(Action::End) => break 'replay,
+                    Entry::Action(action) => apply(action, &mut replayed, &mut lattice_b),
+                    Entry::Key(_) => {}
+                }
+            }
+            lattice_b.step(replayed.temperature, replayed.chem_potential);
+            step += 1;
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(step, 307);
+        assert!(replayed == live);
+        assert!(lattice_b.grid == lattice_a.grid);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::core::Lattice;
+use crate::fft::{Complex, Fft2};
+use std::f64::consts::PI;
+
+/// Static structure factor `S(q) = ⟨|ρ(q)|²⟩ / N` averaged over every
+/// configuration passed to `accumulate`.
+///
+/// The forward peak at `q = 0` is made connected, `S(0) = N (⟨ρ²⟩ − ⟨ρ⟩²)`,
+/// so that it measures the compressibility rather than `N ⟨ρ⟩²`.
+pub struct StructureFactor {
+    width: usize,
+    height: usize,
+    fft: Fft2,
+    sum_power: Vec<f64>,
+    sum_density: f64,
+    samples: usize,
+}
+
+impl StructureFactor {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            fft: Fft2::new(width, height),
+            sum_power: vec![0.0; width * height],
+            sum_density: 0.0,
+            samples: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[allow(dead_code)] // GUI only
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    #[allow(dead_code)] // GUI only
+    pub fn reset(&mut self) {
+        self.sum_power.fill(0.0);
+        self.sum_density = 0.0;
+        self.samples = 0;
+    }
+
+    pub fn accumulate(&mut self, lattice: &Lattice) {
+        debug_assert_eq!((lattice.width, lattice.height), (self.width, self.height));
+        let n_sites = (self.width * self.height) as f64;
+        let mut field: Vec<Complex> = lattice
+            .occupations()
+            .into_iter()
+            .map(|n| Complex::new(n, 0.0))
+            .collect();
+        self.fft.process(&mut field, false);
+        for (sum, v) in self.sum_power.iter_mut().zip(&field) {
+            *sum += v.norm_sqr() / n_sites;
+        }
+        self.sum_density += lattice.molecule_count() as f64 / n_sites;
+        self.samples += 1;
+    }
+
+    /// Wave vector of FFT bin `(ix, iy)`, folded into `[-π, π)`.
+    pub fn wave_vector(&self, ix: usize, iy: usize) -> (f64, f64) {
+        let fold = |i: usize, n: usize| {
+            let k = if 2 * i >= n {
+                i as f64 - n as f64
+            } else {
+                i as f64
+            };
+            2.0 * PI * k / n as f64
+        };
+        (fold(ix, self.width), fold(iy, self.height))
+    }
+
+    /// `S(qx, qy)` stored as `[ix * height + iy]` in FFT bin order.
+    pub fn values(&self) -> Vec<f64> {
+        if self.samples == 0 {
+            return vec![0.0; self.width * self.height];
+        }
+        let s = self.samples as f64;
+        let mut values: Vec<f64> = self.sum_power.iter().map(|p| p / s).collect();
+        let rho = self.sum_density / s;
+        values[0] -= (self.width * self.height) as f64 * rho * rho;
+        values
+    }
+
+    /// Characteristic domain size `2π / ⟨|q|⟩`, with the first moment of `S(q)`
+    /// taken over the modes `0 < |q| ≤ q_max`. A cutoff below `π` keeps the
+    /// flat large-`q` thermal background from dominating the moment.
+    pub fn domain_size(&self, q_max: f64) -> Option<f64> {
+        let values = self.values();
+        let (mut moment, mut norm) = (0.0, 0.0);
+        for ix in 0..self.width {
+            for iy in 0..self.height {
+                let (qx, qy) = self.wave_vector(ix, iy);
+                let q = (qx * qx + qy * qy).sqrt();
+                if q > 0.0 && q <= q_max {
+                    let s = values[ix * self.height + iy];
+                    moment += q * s;
+                    norm += s;
+                }
+            }
+        }
+        (moment > 0.0).then(|| 2.0 * PI * norm / moment)
+    }
+
+    /// `S(|q|)` averaged over shells of width `2π / min(width, height)` up to
+    /// `|q| = π`, returned as `(mean |q|, mean S, number of modes)` for every
+    /// non-empty shell.
+    pub fn radial_profile(&self) -> Vec<(f64, f64, usize)> {
+        let values = self.values();
+        let dq = 2.0 * PI / self.width.min(self.height) as f64;
+        let n_bins = (PI / dq).round() as usize + 1;
+        let mut sum_q = vec![0.0; n_bins];
+        let mut sum_s = vec![0.0; n_bins];
+        let mut counts = vec![0usize; n_bins];
+        for ix in 0..self.width {
+            for iy in 0..self.height {
+                let (qx, qy) = self.wave_vector(ix, iy);
+                let q = (qx * qx + qy * qy).sqrt();
+                let bin = (q / dq).round() as usize;
+                if bin < n_bins {
+                    sum_q[bin] += q;
+                    sum_s[bin] += values[ix * self.height + iy];
+                    counts[bin] += 1;
+                }
+            }
+        }
+        (0..n_bins)
+            .filter(|&b| counts[b] > 0)
+            .map(|b| {
+                let c = counts[b] as f64;
+                (sum_q[b] / c, sum_s[b] / c, counts[b])
+            })
+            .collect()
+    }
+}
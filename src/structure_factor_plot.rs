@@ -0,0 +1,187 @@
+use crate::core::Lattice;
+use crate::structure_factor::StructureFactor;
+use crate::{phase_color_bright, phase_color_dark};
+use macroquad::prelude::*;
+use std::f64::consts::PI;
+
+const DOMAIN_Q_MAX: f64 = PI / 2.0;
+
+pub struct StructureFactorPanel {
+    sf: StructureFactor,
+    params: (f32, f32),
+}
+
+impl StructureFactorPanel {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            sf: StructureFactor::new(width, height),
+            params: (f32::NAN, f32::NAN),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.sf.reset();
+    }
+
+    /// Adds the current configuration to the running average, which restarts
+    /// whenever T, µ or the lattice size change.
+    pub fn update(&mut self, lattice: &Lattice, temp: f32, chem_potential: f32) {
+        if (lattice.width, lattice.height) != (self.sf.width(), self.sf.height()) {
+            self.sf = StructureFactor::new(lattice.width, lattice.height);
+        }
+        if self.params != (temp, chem_potential) {
+            self.params = (temp, chem_potential);
+            self.sf.reset();
+        }
+        self.sf.accumulate(lattice);
+    }
+
+    pub fn draw(&self, rect: Rect) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color_u8!(10, 10, 10, 200));
+        let heading = TextParams {
+            font_size: 28,
+            color: YELLOW,
+            ..Default::default()
+        };
+        draw_text_ex(
+            "Structure Factor S(q)",
+            rect.x + 14.0,
+            rect.y + 24.0,
+            heading,
+        );
+        let info = TextParams {
+            font_size: 18,
+            color: LIGHTGRAY,
+            ..Default::default()
+        };
+        let domain = self
+            .sf
+            .domain_size(DOMAIN_Q_MAX)
+            .map(|l| format!(", domain size 2π/<q> = {l:.2}"))
+            .unwrap_or_default();
+        draw_text_ex(
+            &format!("{} sweeps averaged{domain}", self.sf.samples()),
+            rect.x + 14.0,
+            rect.y + 46.0,
+            info,
+        );
+        if self.sf.samples() == 0 {
+            return;
+        }
+
+        let values = self.sf.values();
+        let (w, h) = (self.sf.width(), self.sf.height());
+        let top = rect.y + 60.0;
+        let side = (rect.w - 28.0).min(rect.h * 0.5).max(10.0);
+        let image_rect = Rect::new(rect.x + (rect.w - side) / 2.0, top, side, side);
+        self.draw_map(&values, w, h, image_rect);
+
+        let plot_top = image_rect.y + image_rect.h + 30.0;
+        let plot_rect = Rect::new(
+            rect.x + 60.0,
+            plot_top,
+            rect.w - 80.0,
+            (rect.y + rect.h - 40.0 - plot_top).max(10.0),
+        );
+        self.draw_radial(plot_rect);
+    }
+
+    /// Log-scaled `S(qx, qy)` with `q = 0` in the centre; the connected forward
+    /// peak is left out of the colour scale.
+    fn draw_map(&self, values: &[f64], w: usize, h: usize, rect: Rect) {
+        let s_max = values.iter().skip(1).cloned().fold(0.0_f64, f64::max);
+        let norm = (1.0 + s_max).ln().max(1e-9);
+        let cell_w = rect.w / w as f32;
+        let cell_h = rect.h / h as f32;
+        let low = phase_color_dark();
+        let high = phase_color_bright();
+        for i in 0..w {
+            let ix = (i + w - w / 2) % w;
+            for j in 0..h {
+                let iy = (j + h - h / 2) % h;
+                let t = ((1.0 + values[ix * h + iy].max(0.0)).ln() / norm).clamp(0.0, 1.0) as f32;
+                let color = Color {
+                    r: low.r + t * (high.r - low.r),
+                    g: low.g + t * (high.g - low.g),
+                    b: low.b + t * (high.b - low.b),
+                    a: 1.0,
+                };
+                draw_rectangle(
+                    rect.x + i as f32 * cell_w,
+                    rect.y + j as f32 * cell_h,
+                    cell_w,
+                    cell_h,
+                    color,
+                );
+            }
+        }
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+        draw_text(
+            "qx",
+            rect.x + rect.w / 2.0 - 8.0,
+            rect.y + rect.h + 18.0,
+            18.0,
+            WHITE,
+        );
+        draw_text("qy", rect.x - 24.0, rect.y + rect.h / 2.0, 18.0, WHITE);
+    }
+
+    fn draw_radial(&self, rect: Rect) {
+        let profile: Vec<(f64, f64, usize)> = self
+            .sf
+            .radial_profile()
+            .into_iter()
+            .filter(|&(q, _, _)| q > 0.0)
+            .collect();
+        let s_max = profile
+            .iter()
+            .map(|p| p.1)
+            .fold(0.0_f64, f64::max)
+            .max(1e-6);
+
+        draw_line(
+            rect.x,
+            rect.y + rect.h,
+            rect.x + rect.w,
+            rect.y + rect.h,
+            1.0,
+            LIGHTGRAY,
+        );
+        draw_line(rect.x, rect.y, rect.x, rect.y + rect.h, 1.0, LIGHTGRAY);
+        draw_text(
+            "|q|",
+            rect.x + rect.w / 2.0 - 8.0,
+            rect.y + rect.h + 20.0,
+            18.0,
+            WHITE,
+        );
+        draw_text("S", rect.x - 20.0, rect.y + rect.h / 2.0, 18.0, WHITE);
+        draw_text("0", rect.x - 4.0, rect.y + rect.h + 16.0, 16.0, LIGHTGRAY);
+        draw_text(
+            "π",
+            rect.x + rect.w - 4.0,
+            rect.y + rect.h + 16.0,
+            16.0,
+            LIGHTGRAY,
+        );
+        draw_text(
+            &format!("{s_max:.2}"),
+            rect.x - 48.0,
+            rect.y + 6.0,
+            16.0,
+            LIGHTGRAY,
+        );
+
+        let to_screen = |q: f64, s: f64| {
+            (
+                rect.x + (q / PI) as f32 * rect.w,
+                rect.y + rect.h - (s / s_max) as f32 * rect.h,
+            )
+        };
+        for pair in profile.windows(2) {
+            let (x1, y1) = to_screen(pair[0].0, pair[0].1);
+            let (x2, y2) = to_screen(pair[1].0, pair[1].1);
+            draw_line(x1, y1, x2, y2, 2.0, YELLOW);
+        }
+    }
+}
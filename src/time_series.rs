@@ -0,0 +1,387 @@
+use crate::plot::Axes;
+use macroquad::prelude::*;
+
+/// Fixed-capacity history of `(sweep, value)` samples; the oldest sample is
+/// overwritten once full.
+pub struct TimeSeriesRingBuffer {
+    buffer: Vec<(u64, f32)>,
+    capacity: usize,
+    head: usize,
+    len: usize,
+}
+
+impl TimeSeriesRingBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: vec![(0, 0.0); capacity],
+            capacity,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, sweep: u64, value: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.buffer[self.head] = (sweep, value);
+        self.head = (self.head + 1) % self.capacity;
+        if self.len < self.capacity {
+            self.len += 1;
+        }
+    }
+
+    pub fn iter_in_order(&self) -> impl Iterator<Item = (u64, f32)> + '_ {
+        let start = (self.head + self.capacity - self.len) % self.capacity;
+        (0..self.len).map(move |i| {
+            let idx = (start + i) % self.capacity;
+            self.buffer[idx]
+        })
+    }
+
+    /// First and last sweep held.
+    fn sweeps(&self) -> Option<(u64, u64)> {
+        let first = self.iter_in_order().next()?;
+        let last = self.buffer[(self.head + self.capacity - 1) % self.capacity];
+        Some((first.0, last.0))
+    }
+
+    /// Smallest and largest value held.
+    fn value_range(&self) -> Option<(f32, f32)> {
+        self.iter_in_order()
+            .map(|(_, v)| v)
+            .filter(|v| v.is_finite())
+            .fold(None, |range, v| match range {
+                None => Some((v, v)),
+                Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+            })
+    }
+
+    fn mean(&self) -> Option<f32> {
+        (self.len > 0).then(|| self.iter_in_order().map(|(_, v)| v).sum::<f32>() / self.len as f32)
+    }
+
+    fn latest(&self) -> Option<f32> {
+        (self.len > 0).then(|| self.buffer[(self.head + self.capacity - 1) % self.capacity].1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Observable {
+    Density,
+    /// Energy per site without the `-µN` term.
+    Energy,
+    /// Largest periodic cluster as a fraction of all sites.
+    LargestCluster,
+    /// `G / G_max` between the left and right edges.
+    Conductance,
+    /// Accepted Metropolis flips per attempted flip.
+    Acceptance,
+    Interface,
+}
+
+impl Observable {
+    pub const ALL: [Observable; 6] = [
+        Observable::Density,
+        Observable::Energy,
+        Observable::LargestCluster,
+        Observable::Conductance,
+        Observable::Acceptance,
+        Observable::Interface,
+    ];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&o| o == self).unwrap_or(0)
+    }
+
+    fn tab(self) -> &'static str {
+        match self {
+            Observable::Density => "density",
+            Observable::Energy => "energy",
+            Observable::LargestCluster => "cluster",
+            Observable::Conductance => "conduct",
+            Observable::Acceptance => "accept",
+            Observable::Interface => "iface",
+        }
+    }
+
+    fn axis_label(self) -> &'static str {
+        match self {
+            Observable::Density => "ρ",
+            Observable::Energy => "E / V",
+            Observable::LargestCluster => "S_max / V",
+            Observable::Conductance => "G / G_max",
+            Observable::Acceptance => "acceptance",
+            Observable::Interface => "ρ_interface",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Observable::Density => YELLOW,
+            Observable::Energy => ORANGE,
+            Observable::LargestCluster => LIME,
+            Observable::Conductance => PINK,
+            Observable::Acceptance => VIOLET,
+            Observable::Interface => SKYBLUE,
+        }
+    }
+}
+
+/// Samples averaged by the running-mean curve.
+const RUNNING_MEAN: usize = 50;
+const TAB_HEIGHT: f32 = 24.0;
+
+/// Popup plotting the recent history of several observables against the
+/// sweep number, either one per tab or all stacked on shared sweep axes.
+/// Each plot scales to the samples currently held and overlays their
+/// running mean.
+pub struct TimeSeriesPopup {
+    series: Vec<TimeSeriesRingBuffer>,
+    is_open: bool,
+    selected: Observable,
+    stacked: bool,
+}
+
+impl TimeSeriesPopup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            series: Observable::ALL
+                .iter()
+                .map(|_| TimeSeriesRingBuffer::with_capacity(capacity))
+                .collect(),
+            is_open: false,
+            selected: Observable::Density,
+            stacked: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_open = !self.is_open;
+    }
+
+    pub fn toggle_stacked(&mut self) {
+        self.stacked = !self.stacked;
+    }
+
+    /// Opens the popup on the tab of `observable`.
+    pub fn select(&mut self, observable: Observable) {
+        self.is_open = true;
+        self.stacked = false;
+        self.selected = observable;
+    }
+
+    /// Whether `observable` is on screen; costly observables are only
+    /// measured while they are.
+    pub fn shows(&self, observable: Observable) -> bool {
+        self.is_open && (self.stacked || self.selected == observable)
+    }
+
+    pub fn record(&mut self, observable: Observable, sweep: u64, value: f32) {
+        self.series[observable.index()].push(sweep, value);
+    }
+
+    pub fn is_mouse_over(&self, rect: Rect) -> bool {
+        self.is_open && rect.contains(mouse_position().into())
+    }
+
+    /// Tabs along the top of `rect`: one per observable and a last one for
+    /// the stacked view.
+    fn tabs(rect: Rect) -> impl Iterator<Item = (Option<Observable>, Rect)> {
+        let n = Observable::ALL.len() + 1;
+        let w = (rect.w - 16.0) / n as f32;
+        Observable::ALL
+            .into_iter()
+            .map(Some)
+            .chain([None])
+            .enumerate()
+            .map(move |(i, o)| {
+                let tab = Rect::new(
+                    rect.x + 8.0 + i as f32 * w,
+                    rect.y + 6.0,
+                    w - 4.0,
+                    TAB_HEIGHT,
+                );
+                (o, tab)
+            })
+    }
+
+    /// Switches tabs on a click inside the popup drawn in `rect`.
+    pub fn handle_click(&mut self, rect: Rect) {
+        if !self.is_open || !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        let mouse: Vec2 = mouse_position().into();
+        match Self::tabs(rect).find(|(_, tab)| tab.contains(mouse)) {
+            Some((Some(observable), _)) => self.select(observable),
+            Some((None, _)) => self.toggle_stacked(),
+            None => {}
+        }
+    }
+
+    pub fn draw(&self, rect: Rect) {
+        if !self.is_open {
+            return;
+        }
+        let bg = Color::new(0.05, 0.05, 0.05, 0.95);
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, bg);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, GRAY);
+
+        for (observable, tab) in Self::tabs(rect) {
+            let (label, color, active) = match observable {
+                Some(o) => (o.tab(), o.color(), !self.stacked && o == self.selected),
+                None => ("stacked", WHITE, self.stacked),
+            };
+            if active {
+                draw_rectangle(
+                    tab.x,
+                    tab.y,
+                    tab.w,
+                    tab.h,
+                    Color::new(0.25, 0.25, 0.25, 1.0),
+                );
+            }
+            draw_rectangle_lines(tab.x, tab.y, tab.w, tab.h, 1.0, GRAY);
+            let dims = measure_text(label, None, 15, 1.0);
+            draw_text(
+                label,
+                tab.x + (tab.w - dims.width) / 2.0,
+                tab.y + 17.0,
+                15.0,
+                color,
+            );
+        }
+
+        let top = rect.y + TAB_HEIGHT + 34.0;
+        let plot_rect = Rect::new(
+            rect.x + 62.0,
+            top,
+            rect.w - 78.0,
+            rect.y + rect.h - 44.0 - top,
+        );
+        if plot_rect.w <= 0.0 || plot_rect.h <= 0.0 {
+            return;
+        }
+
+        if !self.stacked {
+            let series = &self.series[self.selected.index()];
+            let Some(sweeps) = series.sweeps() else {
+                draw_text(
+                    "no samples yet",
+                    plot_rect.x,
+                    plot_rect.y + 20.0,
+                    18.0,
+                    GRAY,
+                );
+                return;
+            };
+            let axes = Axes::new(plot_rect, sweep_range(sweeps), value_range(series));
+            draw_rectangle_lines(
+                plot_rect.x,
+                plot_rect.y,
+                plot_rect.w,
+                plot_rect.h,
+                1.0,
+                LIGHTGRAY,
+            );
+            axes.draw_x_axis(6, "sweep");
+            axes.draw_y_axis(5, self.selected.axis_label());
+            draw_series(axes, series, self.selected.color());
+            draw_summary(axes, series);
+            return;
+        }
+
+        // All observables on one shared sweep range, one row each.
+        let sweeps = self
+            .series
+            .iter()
+            .filter_map(TimeSeriesRingBuffer::sweeps)
+            .reduce(|(a, b), (c, d)| (a.min(c), b.max(d)));
+        let Some(sweeps) = sweeps else {
+            draw_text(
+                "no samples yet",
+                plot_rect.x,
+                plot_rect.y + 20.0,
+                18.0,
+                GRAY,
+            );
+            return;
+        };
+        let rows = Observable::ALL.len();
+        let row_h = (plot_rect.h + 20.0) / rows as f32;
+        for (i, observable) in Observable::ALL.into_iter().enumerate() {
+            let series = &self.series[i];
+            let row = Rect::new(
+                plot_rect.x,
+                plot_rect.y - 20.0 + i as f32 * row_h + 4.0,
+                plot_rect.w,
+                row_h - 4.0,
+            );
+            let axes = Axes::new(row, sweep_range(sweeps), value_range(series));
+            draw_rectangle_lines(row.x, row.y, row.w, row.h, 1.0, GRAY);
+            axes.draw_y_axis(2, "");
+            if i + 1 == rows {
+                axes.draw_x_axis(6, "sweep");
+            }
+            draw_series(axes, series, observable.color());
+            draw_text(
+                observable.axis_label(),
+                row.x + 4.0,
+                row.y + 14.0,
+                16.0,
+                observable.color(),
+            );
+        }
+    }
+}
+
+fn sweep_range((first, last): (u64, u64)) -> (f32, f32) {
+    (first as f32, last.max(first + 1) as f32)
+}
+
+/// Range of the held values with a small margin, widened when they are
+/// (nearly) constant so the trace stays visible.
+fn value_range(series: &TimeSeriesRingBuffer) -> (f32, f32) {
+    let Some((lo, hi)) = series.value_range() else {
+        return (0.0, 1.0);
+    };
+    let span = hi - lo;
+    let pad = if span < 1e-6 {
+        0.01 * lo.abs().max(1.0)
+    } else {
+        0.05 * span
+    };
+    (lo - pad, hi + pad)
+}
+
+/// The samples and their trailing running mean.
+fn draw_series(axes: Axes, series: &TimeSeriesRingBuffer, color: Color) {
+    let mean_color = Color::new(1.0, 1.0, 1.0, 0.6);
+    let samples: Vec<(u64, f32)> = series.iter_in_order().collect();
+    let mut sum = 0.0;
+    let mut prev: Option<(Vec2, Vec2)> = None;
+    for (i, &(sweep, value)) in samples.iter().enumerate() {
+        sum += value;
+        if i >= RUNNING_MEAN {
+            sum -= samples[i - RUNNING_MEAN].1;
+        }
+        let mean = sum / (i + 1).min(RUNNING_MEAN) as f32;
+        let point = axes.to_screen(sweep as f32, value);
+        let mean_point = axes.to_screen(sweep as f32, mean);
+        if let Some((p, m)) = prev {
+            draw_line(p.x, p.y, point.x, point.y, 1.5, color);
+            draw_line(m.x, m.y, mean_point.x, mean_point.y, 1.5, mean_color);
+        }
+        prev = Some((point, mean_point));
+    }
+}
+
+fn draw_summary(axes: Axes, series: &TimeSeriesRingBuffer) {
+    let (Some(latest), Some(mean)) = (series.latest(), series.mean()) else {
+        return;
+    };
+    let text = format!("latest {latest:.4}   window mean {mean:.4}");
+    let dims = measure_text(&text, None, 16, 1.0);
+    let r = axes.rect;
+    draw_text(&text, r.x + r.w - dims.width, r.y - 8.0, 16.0, LIGHTGRAY);
+}